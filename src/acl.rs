@@ -0,0 +1,72 @@
+const ALL_USERS: &str = "http://acs.amazonaws.com/groups/global/AllUsers";
+const AUTHENTICATED_USERS: &str = "http://acs.amazonaws.com/groups/global/AuthenticatedUsers";
+
+/// A single permission granted on an object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grant {
+    /// The canonical user id, email address or group uri that holds the permission.
+    pub grantee: String,
+    /// The permission granted, e.g. `READ` or `FULL_CONTROL`.
+    pub permission: String,
+}
+
+/// The access control list of an object.
+///
+/// Grant sets that correspond to one of S3's canned ACLs are reported as that ACL,
+/// anything else is returned as `Custom`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Acl {
+    Private,
+    PublicRead,
+    PublicReadWrite,
+    AuthenticatedRead,
+    Custom(Vec<Grant>),
+}
+
+impl Acl {
+    pub(crate) fn from_grants(owner_id: Option<&str>, grants: Vec<Grant>) -> Self {
+        let mut others = grants
+            .iter()
+            .filter(|grant| {
+                !(Some(grant.grantee.as_str()) == owner_id && grant.permission == "FULL_CONTROL")
+            })
+            .map(|grant| (grant.grantee.as_str(), grant.permission.as_str()))
+            .collect::<Vec<(&str, &str)>>();
+        others.sort_unstable();
+
+        if others.len() == grants.len() {
+            return Acl::Custom(grants);
+        }
+
+        match others.as_slice() {
+            [] => Acl::Private,
+            [(ALL_USERS, "READ")] => Acl::PublicRead,
+            [(ALL_USERS, "READ"), (ALL_USERS, "WRITE")] => Acl::PublicReadWrite,
+            [(AUTHENTICATED_USERS, "READ")] => Acl::AuthenticatedRead,
+            _ => Acl::Custom(grants),
+        }
+    }
+
+    /// Returns `true` if anyone on the internet can read the object.
+    /// ```
+    ///   use s3_fs::acl::{Acl, Grant};
+    ///   assert!(Acl::PublicRead.is_public());
+    ///   assert!(!Acl::Private.is_public());
+    ///
+    ///   let grant = Grant {
+    ///       grantee: "http://acs.amazonaws.com/groups/global/AllUsers".to_string(),
+    ///       permission: "FULL_CONTROL".to_string(),
+    ///   };
+    ///   assert!(Acl::Custom(vec![grant]).is_public());
+    ///```
+    pub fn is_public(&self) -> bool {
+        match self {
+            Acl::PublicRead | Acl::PublicReadWrite => true,
+            Acl::Private | Acl::AuthenticatedRead => false,
+            Acl::Custom(grants) => grants.iter().any(|grant| {
+                grant.grantee == ALL_USERS
+                    && (grant.permission == "READ" || grant.permission == "FULL_CONTROL")
+            }),
+        }
+    }
+}
@@ -1,3 +1,8 @@
+use crate::errors::S3PathError;
+use percent_encoding::percent_decode_str;
+use rusoto_core::Region;
+use std::str::FromStr;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct BucketConfig {
     /// The name of the bucket.
@@ -9,11 +14,18 @@ pub struct BucketConfig {
 impl BucketConfig {
     /// Create a new bucket configuration from a path. This can either be
     /// a full s3 path or a shortened path
+    ///
+    /// The path is normalized: repeated slashes are collapsed, and a trailing slash is kept on the
+    /// key to mark a directory, so `/bucket//dir/` and `/bucket/dir/` name the same key `dir/`,
+    /// while `/bucket/dir` names the object `dir`.
     /// ```
     ///
     ///   use s3_fs::bucket::BucketConfig;
     ///   let bucket_config = BucketConfig::from_path("s3://bucket/key");
-    ///   assert_eq!(bucket_config, BucketConfig{name: "bucket".to_string(), key: "key".to_string()})
+    ///   assert_eq!(bucket_config, BucketConfig{name: "bucket".to_string(), key: "key".to_string()});
+    ///
+    ///   let bucket_config = BucketConfig::from_path("/bucket//some//dir/");
+    ///   assert_eq!(bucket_config, BucketConfig{name: "bucket".to_string(), key: "some/dir/".to_string()});
     ///
     ///```
     ///
@@ -25,6 +37,77 @@ impl BucketConfig {
         BucketConfig { name, key }
     }
 
+    /// Create a bucket configuration from an S3 URL, also returning the region named in its host.
+    ///
+    /// Virtual-hosted (`https://bucket.s3.region.amazonaws.com/key`) and path-style
+    /// (`https://s3.region.amazonaws.com/bucket/key`) URLs are accepted, with or without a region,
+    /// as well as `s3://bucket/key`. The key is percent-decoded.
+    /// ```
+    ///
+    ///   use rusoto_core::Region;
+    ///   use s3_fs::bucket::BucketConfig;
+    ///   let (bucket_config, region) =
+    ///       BucketConfig::from_uri("https://bucket.s3.eu-west-1.amazonaws.com/some%20dir/key").unwrap();
+    ///   assert_eq!(bucket_config, BucketConfig{name: "bucket".to_string(), key: "some dir/key".to_string()});
+    ///   assert_eq!(region, Some(Region::EuWest1));
+    ///
+    ///   let (bucket_config, region) = BucketConfig::from_uri("https://s3.amazonaws.com/bucket/key").unwrap();
+    ///   assert_eq!(bucket_config, BucketConfig{name: "bucket".to_string(), key: "key".to_string()});
+    ///   assert_eq!(region, None);
+    ///
+    ///   let (bucket_config, region) =
+    ///       BucketConfig::from_uri("https://s3-logs.s3.us-west-2.amazonaws.com/key").unwrap();
+    ///   assert_eq!(bucket_config, BucketConfig{name: "s3-logs".to_string(), key: "key".to_string()});
+    ///   assert_eq!(region, Some(Region::UsWest2));
+    ///
+    ///```
+    pub fn from_uri(uri: &str) -> Result<(Self, Option<Region>), S3PathError> {
+        if uri.starts_with("s3://") {
+            return Ok((Self::from_path(uri), None));
+        }
+
+        let location = uri
+            .strip_prefix("https://")
+            .or_else(|| uri.strip_prefix("http://"))
+            .ok_or(S3PathError::InvalidUri)?;
+        let location = location.split(['?', '#']).next().unwrap_or_default();
+        let (host, path) = location.split_once('/').unwrap_or((location, ""));
+        let path = percent_decode_str(path)
+            .decode_utf8()
+            .map_err(|_| S3PathError::InvalidUri)?;
+
+        let host = host.to_lowercase();
+        let host = host
+            .strip_suffix(".amazonaws.com")
+            .or_else(|| host.strip_suffix(".amazonaws.com.cn"))
+            .ok_or(S3PathError::InvalidUri)?;
+        let labels = host.split('.').collect::<Vec<&str>>();
+        // Only the region and `dualstack` follow the endpoint label, while a bucket name may
+        // itself contain labels like `s3-logs`, so the endpoint is the last match.
+        let endpoint = labels
+            .iter()
+            .rposition(|label| *label == "s3" || label.starts_with("s3-"))
+            .ok_or(S3PathError::InvalidUri)?;
+
+        let region = match labels[endpoint].strip_prefix("s3-") {
+            Some(region) => Some(region),
+            None => labels[endpoint + 1..]
+                .iter()
+                .find(|label| **label != "dualstack")
+                .copied(),
+        }
+        .and_then(|region| Region::from_str(region).ok());
+
+        let path = match endpoint {
+            0 => path.to_string(),
+            _ => format!("{}/{}", labels[..endpoint].join("."), path),
+        };
+        match path.split('/').any(|part| !part.is_empty()) {
+            true => Ok((Self::from_path(path), region)),
+            false => Err(S3PathError::InvalidUri),
+        }
+    }
+
     fn split_path(path: String) -> (String, String) {
         let path = path
             .replace("s3://", "")
@@ -39,7 +122,10 @@ impl BucketConfig {
             panic!("{} is not a valid bucket name.", bucket)
         }
 
-        let key = parts[1..].join("/");
+        let mut key = parts[1..].join("/");
+        if !key.is_empty() && path.ends_with('/') {
+            key.push('/');
+        }
 
         (bucket.to_string(), key)
     }
@@ -0,0 +1,57 @@
+use sha2::{Digest, Sha256};
+
+/// An S3 additional checksum algorithm, verified by S3 on upload and stored alongside the object.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChecksumAlgorithm {
+    Crc32c,
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    /// Computes the base64 encoded checksum of `bytes`, in the same form S3 reports it.
+    /// ```
+    ///   use s3_fs::checksum::ChecksumAlgorithm;
+    ///   assert_eq!(ChecksumAlgorithm::Crc32c.compute(b"123456789"), "4waSgw==");
+    ///   assert_eq!(
+    ///       ChecksumAlgorithm::Sha256.compute(b"hello"),
+    ///       "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ="
+    ///   );
+    ///```
+    pub fn compute(&self, bytes: &[u8]) -> String {
+        match self {
+            ChecksumAlgorithm::Crc32c => base64::encode(crc32c(bytes).to_be_bytes()),
+            ChecksumAlgorithm::Sha256 => base64::encode(Sha256::digest(bytes)),
+        }
+    }
+
+    pub(crate) fn header(&self) -> &str {
+        match self {
+            ChecksumAlgorithm::Crc32c => "x-amz-checksum-crc32c",
+            ChecksumAlgorithm::Sha256 => "x-amz-checksum-sha256",
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            ChecksumAlgorithm::Crc32c => "CRC32C",
+            ChecksumAlgorithm::Sha256 => "SHA256",
+        }
+    }
+}
+
+fn crc32c(bytes: &[u8]) -> u32 {
+    const POLYNOMIAL: u32 = 0x82f6_3b78;
+
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ POLYNOMIAL,
+                _ => crc >> 1,
+            };
+        }
+    }
+
+    !crc
+}
@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusoto_core::credential::{
+    AwsCredentials, CredentialsError, DefaultCredentialsProvider, ProvideAwsCredentials,
+};
+use rusoto_core::param::{Params, ServiceParams};
+use rusoto_core::signature::SignedRequest;
+use rusoto_core::{Client, HttpClient, Region};
+
+/// Obtains temporary credentials by calling STS `AssumeRole` with the default credential chain.
+///
+/// Wrap it in an `AutoRefreshingProvider` so the credentials are renewed once they expire.
+pub struct AssumeRoleProvider {
+    role_arn: String,
+    session_name: String,
+    region: Region,
+}
+
+impl AssumeRoleProvider {
+    pub fn new(role_arn: String, session_name: String, region: Region) -> Self {
+        AssumeRoleProvider {
+            role_arn,
+            session_name,
+            region,
+        }
+    }
+
+    fn extract_tag<'a>(body: &'a str, tag: &str) -> Option<&'a str> {
+        let open = format!("<{}>", tag);
+        let close = format!("</{}>", tag);
+        let start = body.find(&open)? + open.len();
+        let end = body[start..].find(&close)? + start;
+        Some(&body[start..end])
+    }
+
+    fn parse_credentials(body: &str) -> Result<AwsCredentials, CredentialsError> {
+        let field = |tag: &str| {
+            Self::extract_tag(body, tag)
+                .map(|value| value.to_string())
+                .ok_or_else(|| {
+                    CredentialsError::new(format!("AssumeRole response has no {}.", tag))
+                })
+        };
+
+        let expiration = DateTime::parse_from_rfc3339(&field("Expiration")?)
+            .map_err(|e| CredentialsError::new(e.to_string()))?
+            .with_timezone(&Utc);
+
+        Ok(AwsCredentials::new(
+            field("AccessKeyId")?,
+            field("SecretAccessKey")?,
+            Some(field("SessionToken")?),
+            Some(expiration),
+        ))
+    }
+}
+
+#[async_trait]
+impl ProvideAwsCredentials for AssumeRoleProvider {
+    async fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        let http_client = HttpClient::new().map_err(|e| CredentialsError::new(e.to_string()))?;
+        let client = Client::new_with(DefaultCredentialsProvider::new()?, http_client);
+
+        let mut params = Params::new();
+        params.put("Action", "AssumeRole");
+        params.put("Version", "2011-06-15");
+        params.put("RoleArn", &self.role_arn);
+        params.put("RoleSessionName", &self.session_name);
+
+        let mut request = SignedRequest::new("POST", "sts", &self.region, "/");
+        request.set_params(params);
+
+        let mut response = client
+            .sign_and_dispatch(request)
+            .await
+            .map_err(|e| CredentialsError::new(format!("{:?}", e)))?;
+        let response = response
+            .buffer()
+            .await
+            .map_err(|e| CredentialsError::new(e.to_string()))?;
+
+        if !response.status.is_success() {
+            return Err(CredentialsError::new(format!(
+                "AssumeRole failed with status {}: {}",
+                response.status,
+                response.body_as_str()
+            )));
+        }
+
+        Self::parse_credentials(response.body_as_str())
+    }
+}
@@ -1,20 +1,63 @@
+use crate::services::TIMEOUT_MESSAGE;
 use rusoto_core::RusotoError;
+use rusoto_s3::{GetObjectError, S3Error};
 use std::fmt::Debug;
+use std::io;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum S3PathError {
     Unknown,
     ExpiredToken,
     ObjectDoesNotExist,
     ObjectAlreadyExists,
+    NotALink,
+    DecompressionFailed,
+    ChecksumMismatch,
+    Timeout,
+    PreconditionFailed,
+    InvalidMetadata,
+    InvalidRetention,
+    InvalidUri,
+    LocalFile,
+    ObjectArchived,
+    BucketAlreadyOwned,
+    BucketNameTaken,
+    BucketNotEmpty,
+    ShortRead,
+    /// The bucket lives in another region than the one the request was sent to, named here.
+    WrongRegion(String),
+    TooLarge,
+    /// S3 Select rejected the query, with the error code and message it sent.
+    SelectFailed(String),
+    InvalidPath,
+    /// The request is signed by hand and needs the credentials and region of the client, which
+    /// an `S3Client` given as is does not expose.
+    UnknownClient,
+    /// A link was asked to point at an object in another bucket, which redirects cannot reach.
+    LinkAcrossBuckets,
+    AccessDenied,
+    /// S3 could not delete a key in a batch, with the error code and message it sent.
+    DeleteFailed(String),
 }
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum S3PathOp {
     HeadObject,
     GetObject,
     PutObject,
+    ListObjectsV2,
+    GetObjectAcl,
+    CopyObject,
+    DeleteObjects,
+    MultipartUpload,
+    RestoreObject,
+    CreateBucket,
+    DeleteBucket,
+    GetObjectTagging,
+    DeleteObject,
+    SelectObjectContent,
+    ListObjectVersions,
 }
 
 impl std::error::Error for S3PathError {
@@ -24,13 +67,35 @@ impl std::error::Error for S3PathError {
             S3PathError::ExpiredToken => None,
             S3PathError::ObjectDoesNotExist => None,
             S3PathError::ObjectAlreadyExists => None,
+            S3PathError::NotALink => None,
+            S3PathError::DecompressionFailed => None,
+            S3PathError::ChecksumMismatch => None,
+            S3PathError::Timeout => None,
+            S3PathError::PreconditionFailed => None,
+            S3PathError::InvalidMetadata => None,
+            S3PathError::InvalidRetention => None,
+            S3PathError::InvalidUri => None,
+            S3PathError::LocalFile => None,
+            S3PathError::ObjectArchived => None,
+            S3PathError::BucketAlreadyOwned => None,
+            S3PathError::BucketNameTaken => None,
+            S3PathError::BucketNotEmpty => None,
+            S3PathError::ShortRead => None,
+            S3PathError::WrongRegion(_) => None,
+            S3PathError::TooLarge => None,
+            S3PathError::SelectFailed(_) => None,
+            S3PathError::InvalidPath => None,
+            S3PathError::UnknownClient => None,
+            S3PathError::LinkAcrossBuckets => None,
+            S3PathError::AccessDenied => None,
+            S3PathError::DeleteFailed(_) => None,
         }
     }
 }
 
 impl std::fmt::Display for S3PathError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match *self {
+        match self {
             S3PathError::Unknown => {
                 write!(f, "Something unexpected happened.")
             }
@@ -43,10 +108,130 @@ impl std::fmt::Display for S3PathError {
             S3PathError::ObjectAlreadyExists => {
                 write!(f, "The file/folder already exists.")
             }
+            S3PathError::NotALink => {
+                write!(f, "The object is not a symbolic link.")
+            }
+            S3PathError::DecompressionFailed => {
+                write!(f, "The object could not be decompressed.")
+            }
+            S3PathError::ChecksumMismatch => {
+                write!(
+                    f,
+                    "The stored checksum does not match the uploaded content."
+                )
+            }
+            S3PathError::Timeout => {
+                write!(f, "The request timed out.")
+            }
+            S3PathError::PreconditionFailed => {
+                write!(f, "The object has changed since it was last read.")
+            }
+            S3PathError::InvalidMetadata => {
+                write!(f, "The metadata cannot be sent as HTTP headers.")
+            }
+            S3PathError::InvalidRetention => {
+                write!(f, "The retention date must be in the future.")
+            }
+            S3PathError::InvalidUri => {
+                write!(f, "The URI does not point to an S3 object.")
+            }
+            S3PathError::LocalFile => {
+                write!(f, "The local file could not be read or written.")
+            }
+            S3PathError::ObjectArchived => {
+                write!(
+                    f,
+                    "The object is archived and must be restored before it can be read."
+                )
+            }
+            S3PathError::BucketAlreadyOwned => {
+                write!(f, "The bucket already exists and is owned by you.")
+            }
+            S3PathError::BucketNameTaken => {
+                write!(f, "The bucket name is already used by another account.")
+            }
+            S3PathError::BucketNotEmpty => {
+                write!(f, "The bucket is not empty.")
+            }
+            S3PathError::ShortRead => {
+                write!(f, "The object is shorter than the requested range.")
+            }
+            S3PathError::WrongRegion(region) => {
+                write!(f, "The bucket is in {}, send the request there.", region)
+            }
+            S3PathError::TooLarge => {
+                write!(f, "The object is larger than the allowed size.")
+            }
+            S3PathError::SelectFailed(message) => {
+                write!(f, "The query failed: {}", message)
+            }
+            S3PathError::InvalidPath => {
+                write!(f, "The path must be absolute and start with a bucket name.")
+            }
+            S3PathError::LinkAcrossBuckets => {
+                write!(f, "A link must point to an object in its own bucket.")
+            }
+            S3PathError::UnknownClient => {
+                write!(
+                    f,
+                    "The request needs the credentials and region of the client, build the path from credentials."
+                )
+            }
+            S3PathError::AccessDenied => {
+                write!(f, "Access to the object is denied.")
+            }
+            S3PathError::DeleteFailed(message) => {
+                write!(f, "The object could not be deleted: {}", message)
+            }
         }
     }
 }
 
+/// Converts into an `io::Error` of the closest `ErrorKind`, keeping the `S3PathError` as its source,
+/// so S3 errors can be `?`-propagated from functions returning `io::Result`.
+///
+/// ```
+///   use s3_fs::errors::S3PathError;
+///   use std::io;
+///   let error = io::Error::from(S3PathError::ObjectDoesNotExist);
+///   assert_eq!(error.kind(), io::ErrorKind::NotFound);
+///```
+impl From<S3PathError> for io::Error {
+    fn from(error: S3PathError) -> Self {
+        let kind = match error {
+            S3PathError::ObjectDoesNotExist => io::ErrorKind::NotFound,
+            S3PathError::ExpiredToken | S3PathError::AccessDenied => {
+                io::ErrorKind::PermissionDenied
+            }
+            S3PathError::ObjectAlreadyExists
+            | S3PathError::BucketAlreadyOwned
+            | S3PathError::BucketNameTaken => io::ErrorKind::AlreadyExists,
+            S3PathError::Timeout => io::ErrorKind::TimedOut,
+            S3PathError::NotALink
+            | S3PathError::InvalidMetadata
+            | S3PathError::InvalidRetention
+            | S3PathError::InvalidUri
+            | S3PathError::WrongRegion(_)
+            | S3PathError::SelectFailed(_)
+            | S3PathError::InvalidPath
+            | S3PathError::LinkAcrossBuckets => io::ErrorKind::InvalidInput,
+            S3PathError::DecompressionFailed
+            | S3PathError::ChecksumMismatch
+            | S3PathError::TooLarge => io::ErrorKind::InvalidData,
+            S3PathError::ShortRead => io::ErrorKind::UnexpectedEof,
+            S3PathError::BucketNotEmpty => io::ErrorKind::DirectoryNotEmpty,
+            S3PathError::UnknownClient => io::ErrorKind::Unsupported,
+            S3PathError::Unknown
+            | S3PathError::PreconditionFailed
+            | S3PathError::LocalFile
+            | S3PathError::ObjectArchived
+            | S3PathError::DeleteFailed(_) => io::ErrorKind::Other,
+        };
+
+        io::Error::new(kind, error)
+    }
+}
+
 pub fn process_error<E: Debug>(
     e: Option<RusotoError<E>>,
     s3_path_error: Option<S3PathError>,
@@ -56,9 +241,17 @@ pub fn process_error<E: Debug>(
         None => s3_path_error.unwrap(),
         Some(rusoto_error) => match rusoto_error {
             RusotoError::Service(_) => S3PathError::Unknown,
+            RusotoError::HttpDispatch(error) if error.to_string() == TIMEOUT_MESSAGE => {
+                S3PathError::Timeout
+            }
             RusotoError::Unknown(error) => match error.status.as_str() {
                 "400" => S3PathError::ExpiredToken,
-                "404" | "301" => {
+                "412" => S3PathError::PreconditionFailed,
+                "301" => match error.headers.get("x-amz-bucket-region") {
+                    Some(region) => S3PathError::WrongRegion(region.to_string()),
+                    None => S3PathError::Unknown,
+                },
+                "404" => {
                     if let S3PathOp::HeadObject = op {
                         S3PathError::ObjectDoesNotExist
                     } else {
@@ -71,3 +264,27 @@ pub fn process_error<E: Debug>(
         },
     }
 }
+
+/// Like [process_error] for reads, additionally reporting archived objects that need a restore.
+pub fn process_get_object_error(e: RusotoError<GetObjectError>) -> S3PathError {
+    match e {
+        RusotoError::Service(GetObjectError::InvalidObjectState(_)) => S3PathError::ObjectArchived,
+        e => process_error(Some(e), None, S3PathOp::GetObject),
+    }
+}
+
+/// Maps a key DeleteObjects could not delete to the matching error, keeping S3's code and message
+/// for the ones without one.
+pub fn process_delete_error(error: &S3Error) -> S3PathError {
+    match error.code.as_deref() {
+        Some("AccessDenied") => S3PathError::AccessDenied,
+        Some("NoSuchKey") | Some("NoSuchVersion") => S3PathError::ObjectDoesNotExist,
+        Some("ExpiredToken") => S3PathError::ExpiredToken,
+        Some("SlowDown") | Some("RequestTimeout") => S3PathError::Timeout,
+        code => S3PathError::DeleteFailed(format!(
+            "{}: {}",
+            code.unwrap_or("Unknown"),
+            error.message.as_deref().unwrap_or_default()
+        )),
+    }
+}
@@ -0,0 +1,127 @@
+use md5::{Digest, Md5};
+use std::fmt::{Display, Formatter};
+
+/// The entity tag S3 returns for an object.
+///
+/// The ETag of an object uploaded in a single request is the MD5 of its contents, but the ETag of
+/// a multipart upload is the MD5 of its parts' MD5s followed by `-` and the number of parts.
+/// Two multipart uploads of the same contents with different part sizes get different ETags.
+///
+/// ```
+///   use s3_fs::etag::ETag;
+///   let single = ETag::from("\"d41d8cd98f00b204e9800998ecf8427e\"");
+///   assert!(!single.is_multipart());
+///   assert_eq!(single.md5(), Some("d41d8cd98f00b204e9800998ecf8427e"));
+///
+///   let multipart = ETag::from("\"3858f62230ac3c915f300c664312c11f-9\"");
+///   assert!(multipart.is_multipart());
+///   assert_eq!(multipart.parts(), Some(9));
+///   assert_eq!(multipart.md5(), None);
+///
+///   assert_eq!(single, ETag::from("d41d8cd98f00b204e9800998ecf8427e"));
+///```
+#[derive(Clone, Debug, Eq)]
+pub struct ETag(String);
+
+impl ETag {
+    /// The ETag S3 assigns to `contents` uploaded in a single request.
+    ///
+    /// ```
+    ///   use s3_fs::etag::ETag;
+    ///   assert_eq!(ETag::of_contents(b""), ETag::from("d41d8cd98f00b204e9800998ecf8427e"));
+    ///```
+    pub fn of_contents(contents: &[u8]) -> Self {
+        ETag(to_hex(&Md5::digest(contents)))
+    }
+
+    /// The ETag exactly as S3 returned it, quotes included, as expected by `If-Match`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The ETag without its surrounding quotes or weak validator prefix.
+    pub fn value(&self) -> &str {
+        let value = self.0.strip_prefix("W/").unwrap_or(&self.0);
+        value.trim_matches('"')
+    }
+
+    /// Returns `true` if the object was written by a multipart upload.
+    pub fn is_multipart(&self) -> bool {
+        self.parts().is_some()
+    }
+
+    /// The number of parts of a multipart upload, parsed from the `-N` suffix.
+    pub fn parts(&self) -> Option<u32> {
+        let (_, parts) = self.value().rsplit_once('-')?;
+        parts.parse().ok()
+    }
+
+    /// The hex encoded MD5 of the contents, which is only known for single-part uploads.
+    pub fn md5(&self) -> Option<&str> {
+        match self.is_multipart() {
+            true => None,
+            false => Some(self.value()),
+        }
+    }
+
+    /// Returns `true` if both ETags are single-part and differ. Unless the objects are encrypted
+    /// with SSE-KMS or SSE-C, whose ETags are not MD5s, this proves the contents differ; see
+    /// [crate::object::ObjectMetadata::md5]. Multipart ETags can differ for identical contents,
+    /// so they never prove a change.
+    pub fn contents_differ(&self, other: &ETag) -> bool {
+        match (self.md5(), other.md5()) {
+            (Some(md5), Some(other_md5)) => md5 != other_md5,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq for ETag {
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()
+    }
+}
+
+impl From<String> for ETag {
+    fn from(e_tag: String) -> Self {
+        ETag(e_tag)
+    }
+}
+
+impl From<&str> for ETag {
+    fn from(e_tag: &str) -> Self {
+        ETag(e_tag.to_string())
+    }
+}
+
+impl Display for ETag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Computes the ETag of a multipart upload as its parts are sent.
+#[derive(Debug, Default)]
+pub(crate) struct MultipartETag {
+    digests: Vec<u8>,
+    parts: usize,
+}
+
+impl MultipartETag {
+    pub(crate) fn add_part(&mut self, part: &[u8]) {
+        self.digests.extend_from_slice(&Md5::digest(part));
+        self.parts += 1;
+    }
+
+    pub(crate) fn finish(&self) -> ETag {
+        ETag(format!(
+            "{}-{}",
+            to_hex(&Md5::digest(&self.digests)),
+            self.parts
+        ))
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
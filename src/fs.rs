@@ -1,6 +1,19 @@
+use crate::checksum::ChecksumAlgorithm;
 use crate::errors::S3PathError;
+use crate::etag::ETag;
+use crate::glob;
+use crate::listing::{DirUsage, Listing, ObjectVersion, ReadDirStream};
+use crate::object::ObjectMetadata;
+use crate::options::{ListOptions, MetadataDirective, TaggingDirective, UploadOptions};
+use crate::reader::S3Reader;
 use crate::s3::S3Path;
-use crate::services::S3Service;
+use crate::services::{S3Service, MAX_COPY_OBJECT_SIZE};
+use crate::sync::{self, BatchResult, MoveReport, SyncOptions, SyncReport};
+use md5::{Digest, Md5};
+use rusoto_s3::StreamingBody;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
 
 #[derive(Debug)]
 struct FS {
@@ -9,10 +22,10 @@ struct FS {
 }
 
 impl FS {
+    /// Does not check that an object exists at `path`: operations that need one report it
+    /// missing as an error.
     pub fn new(path: S3Path) -> Self {
-        Self::ensure_paths_exists(&path).unwrap();
-
-        let service = S3Service::new(path.path.to_str().unwrap().to_string());
+        let service = path.service.clone();
 
         FS { path, service }
     }
@@ -21,59 +34,398 @@ impl FS {
     where
         P: ToString + Copy,
     {
-        let path = S3Path::new(path);
-        let service = S3Service::new(path.path.to_str().unwrap().to_string());
-
-        FS { path, service }
+        Self::new(S3Path::new(path))
     }
 
-    pub fn copy<P>(&self, to: P) -> Result<Option<i64>, S3PathError>
-    where
-        P: ToString + Copy,
-    {
-        let from_content = self.service.get_object_body()?;
-
+    pub fn copy(&self, to: &S3Path) -> Result<Option<i64>, S3PathError> {
+        let into_dir = self.path_in_dir(to);
+        let to = into_dir.as_ref().unwrap_or(to);
         let from_metadata = self.path.metadata()?;
 
-        self.service.write_to_object(
+        if from_metadata.content_length > Some(MAX_COPY_OBJECT_SIZE) {
+            to.service.multipart_copy_from(
+                &self.service,
+                &from_metadata,
+                &UploadOptions::preserving(&from_metadata),
+                &mut |_, _| {},
+            )?;
+
+            return Ok(from_metadata.content_length);
+        }
+
+        let from_content = self.service.get_object_body()?;
+
+        to.service.write_to_object(
             from_metadata.content_length,
             from_content,
-            to,
-            self.path.metadata().unwrap().metadata,
+            from_metadata.metadata.clone(),
+            &UploadOptions::preserving(&from_metadata),
         )?;
 
         Ok(from_metadata.content_length)
     }
 
-    pub fn create_dir(&self, path: &S3Path) -> Result<String, S3PathError> {
-        let dir_name = path.path.to_str().unwrap();
+    /// When `dir` is a directory, i.e. its key is empty or ends with `/`, the path of the
+    /// object with the same file name as this one inside it, like `cp a/b.txt c/` gives `c/b.txt`.
+    fn path_in_dir(&self, dir: &S3Path) -> Option<S3Path> {
+        let dir_key = dir.key();
+        if !dir_key.is_empty() && !dir_key.ends_with('/') {
+            return None;
+        }
+
+        let file_name = self
+            .service
+            .bucket
+            .key
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty())?;
+
+        Some(S3Path::from_service(
+            dir.service.with_key(format!("{}{}", dir_key, file_name)),
+        ))
+    }
+
+    pub fn copy_across(&self, to: &S3Path) -> Result<u64, S3PathError> {
+        let from_metadata = self.path.metadata()?;
+
+        to.service.stream_from(&self.service, &from_metadata)
+    }
+
+    pub fn copy_with_metadata(
+        &self,
+        to: &S3Path,
+        directive: &MetadataDirective,
+    ) -> Result<(), S3PathError> {
+        to.service.copy_from(&self.service, directive)?;
+
+        Ok(())
+    }
+
+    pub fn copy_with_tagging(
+        &self,
+        to: &S3Path,
+        directive: &MetadataDirective,
+        tagging: &TaggingDirective,
+    ) -> Result<(), S3PathError> {
+        to.service
+            .copy_with_tagging(&self.service, directive, tagging)?;
+
+        Ok(())
+    }
+
+    pub fn rename(&self, to: &S3Path, keep_source_on_failure: bool) -> Result<bool, S3PathError> {
+        let from_metadata = self.path.metadata()?;
+
+        match from_metadata.content_length > Some(MAX_COPY_OBJECT_SIZE) {
+            true => to.service.multipart_copy_from(
+                &self.service,
+                &from_metadata,
+                &UploadOptions::preserving(&from_metadata),
+                &mut |_, _| {},
+            )?,
+            false => {
+                to.service
+                    .copy_from(&self.service, &MetadataDirective::Copy)?;
+            }
+        }
+        to.invalidate();
+
+        match self
+            .service
+            .delete_objects(&[self.service.bucket.key.to_string()])
+        {
+            Ok(()) => {
+                self.path.invalidate();
+                Ok(true)
+            }
+            Err(_) if keep_source_on_failure => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn copy_no_overwrite(&self, to: &S3Path) -> Result<Option<i64>, S3PathError> {
+        let into_dir = self.path_in_dir(to);
+        let to = into_dir.as_ref().unwrap_or(to);
+        let from_metadata = self.path.metadata()?;
+
+        // Copies over 5 GiB go through a multipart copy, which cannot be made conditional.
+        match from_metadata.content_length {
+            Some(length) if to.service.signs_raw_requests() && length <= MAX_COPY_OBJECT_SIZE => {
+                to.service
+                    .copy_if_none_match(&self.service, &from_metadata)?;
+                Ok(from_metadata.content_length)
+            }
+            _ => {
+                Self::ensure_path_is_free(&to.service)?;
+                self.copy(to)
+            }
+        }
+    }
+
+    pub fn read(&self, decode: bool) -> Result<Vec<u8>, S3PathError> {
+        self.service.read_contents(decode)
+    }
+
+    pub fn read_limited(&self, max_bytes: u64) -> Result<Vec<u8>, S3PathError> {
+        let content_length = self.path.metadata()?.content_length.unwrap_or_default();
+
+        if content_length as u64 > max_bytes {
+            return Err(S3PathError::TooLarge);
+        }
+
+        // Reading one byte past the limit bounds the memory used even if the object was
+        // replaced by a larger one since the HEAD.
+        let contents = self.service.read_range(0, max_bytes)?;
+        match contents.len() as u64 > max_bytes {
+            true => Err(S3PathError::TooLarge),
+            false => Ok(contents),
+        }
+    }
+
+    pub fn write<C: AsRef<[u8]>>(
+        &self,
+        contents: C,
+        options: &UploadOptions,
+    ) -> Result<(), S3PathError> {
+        options.validate()?;
+        let contents = contents.as_ref().to_vec();
+
+        // S3 only accepts Object Lock uploads that carry a Content-MD5.
+        let mut options = options.clone();
+        if options.get_object_lock().is_some() {
+            options.content_md5 = Some(base64::encode(Md5::digest(&contents)));
+        }
+
+        let expected = ETag::of_contents(&contents);
+        let result = self.service.write_to_object(
+            Some(contents.len() as i64),
+            Some(contents.into()),
+            None,
+            &options,
+        )?;
+
+        match options.get_verify_etag() && result.e_tag.map(ETag::from) != Some(expected) {
+            true => Err(S3PathError::ChecksumMismatch),
+            false => Ok(()),
+        }
+    }
+
+    pub fn write_stream(
+        &self,
+        body: StreamingBody,
+        options: &UploadOptions,
+    ) -> Result<(), S3PathError> {
+        options.validate()?;
+
+        self.service
+            .write_to_object(None, Some(body), None, options)?;
+
+        Ok(())
+    }
+
+    pub fn write_with_checksum<C: AsRef<[u8]>>(
+        &self,
+        contents: C,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<String, S3PathError> {
+        self.service
+            .write_with_checksum(contents.as_ref().to_vec(), algorithm)
+    }
+
+    pub fn write_if_match<C: AsRef<[u8]>>(
+        &self,
+        contents: C,
+        e_tag: &ETag,
+    ) -> Result<ETag, S3PathError> {
+        self.service
+            .write_if_match(contents.as_ref().to_vec(), e_tag)
+    }
+
+    pub fn write_no_overwrite<C: AsRef<[u8]>>(&self, contents: C) -> Result<(), S3PathError> {
+        if !self.service.signs_raw_requests() {
+            Self::ensure_path_is_free(&self.service)?;
+            return self.write(contents, &UploadOptions::default());
+        }
+
+        self.service.write_if_none_match(
+            contents.as_ref().to_vec(),
+            None,
+            &UploadOptions::default(),
+        )
+    }
+
+    pub fn touch(&self) -> Result<(), S3PathError> {
+        match self.service.key_exists(&self.service.bucket.key)? {
+            true => Ok(()),
+            false => self.write([], &UploadOptions::default()),
+        }
+    }
+
+    pub fn objects_exist(&self, keys: &[String]) -> Result<HashMap<String, bool>, S3PathError> {
+        self.service.objects_exist(keys)
+    }
+
+    pub fn create_dir(&self, exist_ok: bool) -> Result<S3Path, S3PathError> {
+        self.service.create_dir(exist_ok)
+    }
+
+    pub fn create_dir_all(&self) -> Result<S3Path, S3PathError> {
+        self.service.create_dir_all()
+    }
+
+    pub fn stat_dir(&self) -> Result<Vec<(S3Path, ObjectMetadata)>, S3PathError> {
+        self.service
+            .with_key(sync::dir_prefix(&self.service.bucket.key))
+            .stat_objects()
+    }
+
+    pub fn remove_dir_forced(&self) -> Result<(), S3PathError> {
+        self.service.remove_marker()
+    }
+
+    pub fn remove_version(&self, version_id: &str) -> Result<(), S3PathError> {
+        self.service.delete_version(Some(version_id))
+    }
+
+    pub fn read_dir(&self, options: &ListOptions) -> Result<Listing, S3PathError> {
+        self.list_service(options).list_objects()
+    }
+
+    fn list_service(&self, options: &ListOptions) -> S3Service {
+        let key = &self.service.bucket.key;
+        let mut service = match options.get_prefix_match() {
+            true => self.service.clone(),
+            false => self.service.with_key(sync::dir_prefix(key)),
+        };
+        service.max_keys = options.get_max_keys();
+        service.recursive = options.get_recursive();
+        service.fetch_owner = options.get_fetch_owner();
+        service
+    }
+
+    pub fn list_versions(&self) -> Result<Vec<ObjectVersion>, S3PathError> {
+        self.service.list_object_versions()
+    }
+
+    pub fn dir_usage(&self) -> Result<DirUsage, S3PathError> {
+        let prefix = sync::dir_prefix(&self.service.bucket.key);
+
+        Ok(self
+            .service
+            .with_key(prefix)
+            .list_all_objects()?
+            .iter()
+            .fold(DirUsage::default(), |usage, object| DirUsage {
+                bytes: usage.bytes + object.size.unwrap_or_default() as u64,
+                objects: usage.objects + 1,
+            }))
+    }
+
+    pub fn remove_matching(&self, pattern: &str) -> Result<BatchResult, S3PathError> {
+        let prefix = sync::dir_prefix(&self.service.bucket.key);
+        let match_key = pattern.contains('/');
+
+        let keys = self
+            .service
+            .with_key(&prefix)
+            .list_all_objects()?
+            .into_iter()
+            .filter_map(|object| object.key)
+            .filter(|key| {
+                let relative_key = &key[prefix.len()..];
+                let name = match match_key {
+                    true => relative_key,
+                    false => relative_key.rsplit('/').next().unwrap_or_default(),
+                };
+                !name.is_empty() && glob::matches(pattern, name)
+            })
+            .collect::<Vec<String>>();
+
+        Ok(self.service.delete_keys(&keys))
+    }
+
+    pub fn find_by_tag(&self, key: &str, value: &str) -> Result<Vec<S3Path>, S3PathError> {
+        let prefix = self
+            .service
+            .with_key(sync::dir_prefix(&self.service.bucket.key));
+        let keys = prefix
+            .list_all_objects()?
+            .into_iter()
+            .filter_map(|object| object.key)
+            .collect();
+
+        Ok(prefix
+            .keys_tagged(keys, key, value)?
+            .into_iter()
+            .map(|key| S3Path::from_service(self.service.with_key(key)))
+            .collect())
+    }
+
+    pub fn read_dir_stream(&self, options: &ListOptions) -> ReadDirStream {
+        ReadDirStream::new(self.list_service(options))
+    }
+
+    pub fn soft_link(&self, target: &S3Path) -> Result<String, S3PathError> {
+        if target.service.bucket.name != self.service.bucket.name {
+            return Err(S3PathError::LinkAcrossBuckets);
+        }
+
+        let location = format!("/{}", target.service.bucket.key);
 
         self.service
-            .write_to_object(None, None, self.service.bucket.key.to_string(), None)?;
+            .write_redirect(self.service.bucket.key.to_string(), location.to_string())?;
+
+        Ok(location)
+    }
 
-        Ok(dir_name.to_string())
+    pub fn read_link(&self) -> Result<S3Path, S3PathError> {
+        match self.service.get_redirect_location()? {
+            Some(location) if location.starts_with('/') => {
+                Ok(S3Path::from_service(self.service.with_key(&location[1..])))
+            }
+            _ => Err(S3PathError::NotALink),
+        }
     }
 
-    fn ensure_paths_exists(path: &S3Path) -> Result<bool, S3PathError> {
-        path.try_exists()
+    fn ensure_path_is_free(service: &S3Service) -> Result<(), S3PathError> {
+        match service.key_exists(&service.bucket.key)? {
+            true => Err(S3PathError::ObjectAlreadyExists),
+            false => Ok(()),
+        }
     }
 }
 
 /// Copies the contents of one S3 object to another. This function will overwrite the contents of `to`.
 /// On success, it returns the content_length of the object
 ///
+/// A `to` starting with `/` or `s3://` is a full path, so the destination can live in a
+/// different bucket from `from`. Any other `to` is a key in the bucket of `from`.
+///
+/// The user metadata, `Cache-Control`, `Content-Disposition` and `Expires` of `from` are
+/// carried over to `to`.
+///
+/// Objects larger than 5 GiB are copied server-side in parts. If such a copy fails, copying
+/// again resumes it from the parts already copied, as long as `from` has not changed since.
+///
+/// Like `cp`, a destination ending with `/` is a directory to copy into, keeping the file name
+/// of `from`: copying `/bucket/a/b.txt` to `/bucket/c/` writes `/bucket/c/b.txt`.
+///
 /// # Example
 ///
 /// ```no_run
 /// use s3_fs::fs;
 /// use s3_fs::s3::S3Path;
-/// let s3_path = S3Path::new("foo/from.txt");
+/// fs::copy(S3Path::new("/foo/a/b.txt"), "/foo/c/");
+/// fs::copy(S3Path::new("/foo/a/b.txt"), "a/b.bak");
+///
+/// let s3_path = S3Path::new("/src-bucket/from.txt");
 ///     fs::copy(
 ///         s3_path,
-///         "foo/to.txt",
+///         "s3://dst-bucket/to.txt",
 ///     );
 ///
-///let copied_path = S3Path::new("foo/to.txt");
+///let copied_path = S3Path::new("/dst-bucket/to.txt");
 /// copied_path.try_exists();
 /// ```
 ///
@@ -85,70 +437,1140 @@ pub fn copy<P>(from: S3Path, to: P) -> Result<Option<i64>, S3PathError>
 where
     P: ToString + Copy,
 {
+    let to = copy_destination(&from, to);
+    copy_to(from, &to)
+}
+
+/// Resolves the `to` of a copy from `from`: a full path if it starts with `/` or `s3://`,
+/// otherwise a key in the bucket of `from`.
+fn copy_destination<P: ToString>(from: &S3Path, to: P) -> S3Path {
+    let to = to.to_string();
+    match to.starts_with('/') || to.starts_with("s3://") {
+        true => S3Path::new(to.as_str()),
+        false => S3Path::from_service(from.service.with_key(&to)),
+    }
+}
+
+/// Copies the contents of one S3 object to another `S3Path`. The write is made with the destination's
+/// client, so `to` can live in another bucket. On success, it returns the content_length of the object
+///
+/// As with [copy], a destination ending with `/` is a directory to copy into. Returns
+/// `S3PathError::ObjectDoesNotExist` if `from` does not exist.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::s3::S3Path;
+/// fs::copy_to(
+///         S3Path::new("/src-bucket/a.txt"),
+///         &S3Path::new("/dst-bucket/a.txt"),
+///     );
+/// ```
+pub fn copy_to(from: S3Path, to: &S3Path) -> Result<Option<i64>, S3PathError> {
     let fs = FS::new(from);
 
     fs.copy(to)
 }
 
-/// Creates a new directory in an s3 bucket.
+/// Copies an S3 object to an `S3Path` reached with other credentials, such as a bucket of
+/// another account. The object is read with the client of `from` and written with the client
+/// of `to`, streaming its parts through so memory stays bounded, since a server-side copy
+/// needs one set of credentials allowed on both buckets. It returns the number of bytes copied.
 ///
+/// As with [copy], the user metadata, `Content-Type`, `Content-Encoding`, `Cache-Control`,
+/// `Content-Disposition` and `Expires` of `from` are carried over to `to`.
 ///
-/// # Note
-/// If you do not pass a full S3 path, the function splits the provided path with "/" and assumes the first part is the bucket.
-/// i.e The bucket name in "foo/bar/doo/dah" is "foo".
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::s3::S3Path;
+/// let partner = S3Path::with_assumed_role(
+///     "/partner-bucket/export.csv",
+///     "arn:aws:iam::210987654321:role/export-reader",
+///     "s3-fs-session",
+/// );
+/// fs::copy_across(partner, S3Path::new("/my-bucket/import.csv"));
+/// ```
+pub fn copy_across(from: S3Path, to: S3Path) -> Result<u64, S3PathError> {
+    let fs = FS::new(from);
+
+    fs.copy_across(&to)
+}
+
+/// Copies an S3 object server-side, without downloading its bytes, choosing whether the
+/// metadata of `from` is kept or replaced on the copy.
+///
+/// Copying an object onto itself with `MetadataDirective::Replace` updates its headers in place.
+/// As with [copy], a `to` without a leading `/` or `s3://` is a key in the bucket of `from`.
 ///
 /// # Example
 ///
 /// ```no_run
 /// use s3_fs::fs;
+/// use s3_fs::options::MetadataDirective;
 /// use s3_fs::s3::S3Path;
-/// fs::create_dir(
-///         "foo/some_dir",
+/// use std::collections::HashMap;
+/// fs::copy_with_metadata(
+///         S3Path::new("/foo/huge.csv"),
+///         "/foo/huge.csv",
+///         &MetadataDirective::Replace {
+///             content_type: Some("text/csv".to_string()),
+///             metadata: HashMap::new(),
+///         },
 ///     );
+/// ```
+pub fn copy_with_metadata<P>(
+    from: S3Path,
+    to: P,
+    directive: &MetadataDirective,
+) -> Result<(), S3PathError>
+where
+    P: ToString + Copy,
+{
+    let to = copy_destination(&from, to);
+    let fs = FS::new(from);
+
+    fs.copy_with_metadata(&to, directive)
+}
+
+/// Copies an object server-side like [copy_with_metadata], also keeping or replacing its tags.
+///
+/// With `TaggingDirective::Copy` the tags of `from` follow the copy, so cost-allocation tags
+/// move with the data. With `TaggingDirective::Replace` the copy is tagged with the given tags
+/// only. The copy is made with a single `CopyObject`, so `from` must be at most 5 GiB.
+/// As with [copy], a `to` without a leading `/` or `s3://` is a key in the bucket of `from`.
+///
+/// # Example
 ///
-/// S3Path::new("foo/some_dir").try_exists();
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::options::{MetadataDirective, TaggingDirective};
+/// use s3_fs::s3::S3Path;
+/// use std::collections::HashMap;
+/// fs::copy_with_tagging(
+///         S3Path::new("/foo/raw/events.json"),
+///         "/foo/processed/events.json",
+///         &MetadataDirective::Copy,
+///         &TaggingDirective::Replace(HashMap::from([("stage".to_string(), "processed".to_string())])),
+///     );
 /// ```
+pub fn copy_with_tagging<P>(
+    from: S3Path,
+    to: P,
+    directive: &MetadataDirective,
+    tagging: &TaggingDirective,
+) -> Result<(), S3PathError>
+where
+    P: ToString + Copy,
+{
+    let to = copy_destination(&from, to);
+    let fs = FS::new(from);
+
+    fs.copy_with_tagging(&to, directive, tagging)
+}
+
+/// Copies the contents of one S3 object to another, refusing to overwrite `to` if it already exists.
+/// On success, it returns the content_length of the object
 ///
-/// # Panics
+/// The copy is streamed through with `If-None-Match: *`, so S3 refuses it even if a concurrent
+/// writer created `to` in the meantime. Returns `S3PathError::ObjectDoesNotExist` if `from` does
+/// not exist.
 ///
-/// Panics if anything goes wrong when making the PutObject call.
-#[allow(clippy::result_unit_err)]
-pub fn create_dir<P>(path: P) -> Result<String, S3PathError>
+/// # Note
+/// Objects over 5 GiB, and destinations built with [S3Path::from_s3_client], are checked with
+/// a HEAD request before the copy instead, so a concurrent writer may still create `to` between
+/// the check and the copy.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::errors::S3PathError;
+/// use s3_fs::fs;
+/// use s3_fs::s3::S3Path;
+/// let copied = fs::copy_no_overwrite(S3Path::new("/foo/from.txt"), "/foo/to.txt");
+///
+/// if let Err(S3PathError::ObjectAlreadyExists) = copied {
+///     println!("/foo/to.txt already exists");
+/// }
+/// ```
+pub fn copy_no_overwrite<P>(from: S3Path, to: P) -> Result<Option<i64>, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::new(from);
+
+    fs.copy_no_overwrite(&S3Path::new(to))
+}
+
+/// Reads the entire contents of an object into a vector of bytes.
+///
+/// The bytes are returned exactly as stored; see [read_decoded] to decompress gzip encoded objects.
+/// A zero-byte object, such as a directory marker, reads as an empty vector.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let contents = fs::read("/foo/bar.txt").unwrap();
+/// ```
+pub fn read<P>(path: P) -> Result<Vec<u8>, S3PathError>
 where
     P: ToString + Copy,
 {
     let fs = FS::from_string(path);
 
-    fs.create_dir(&fs.path)
+    fs.read(false)
 }
 
-/// Recursively create a directory and all of its parent components if they are missing.
+/// Reads the entire contents of an object like [read], unless it is larger than `max_bytes`.
 ///
+/// The size is checked with a HEAD request first, returning `S3PathError::TooLarge` without
+/// downloading anything when it exceeds `max_bytes`. This keeps a server from buffering a huge
+/// object into memory when reading paths supplied by its users.
 ///
 /// # Example
 ///
 /// ```no_run
+/// use s3_fs::errors::S3PathError;
 /// use s3_fs::fs;
-/// use s3_fs::s3::S3Path;
-/// fs::create_dir_all(
-///         "foo/some_dir/bar/",
-///     );
+/// match fs::read_limited("/foo/upload.json", 10 * 1024 * 1024) {
+///     Ok(contents) => println!("{} bytes", contents.len()),
+///     Err(S3PathError::TooLarge) => println!("over 10 MiB"),
+///     Err(e) => println!("{}", e),
+/// }
+/// ```
+pub fn read_limited<P>(path: P, max_bytes: u64) -> Result<Vec<u8>, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.read_limited(max_bytes)
+}
+
+/// Reads the entire contents of an object, transparently decompressing it if it was
+/// stored with `Content-Encoding: gzip`.
+///
+/// Returns `S3PathError::DecompressionFailed` if the body is not valid gzip.
+///
+/// # Example
 ///
-/// S3Path::new("foo/some_dir/").try_exists();
-/// S3Path::new("foo/some/bar/").try_exists();
+/// ```no_run
+/// use s3_fs::fs;
+/// let contents = fs::read_decoded("/foo/app.js").unwrap();
 /// ```
+pub fn read_decoded<P>(path: P) -> Result<Vec<u8>, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.read(true)
+}
+
+/// Reads up to `len` bytes of an S3 object, starting at byte `start`.
 ///
-/// # Panics
+/// Only the bytes that exist are returned, so the result is shorter than `len` when the range
+/// extends past the end of the object, and empty when it starts past the end.
 ///
-/// Panics if anything goes wrong when making the PutObject call.
-#[allow(clippy::result_unit_err)]
-pub fn create_dir_all<P>(path: P)
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let head = fs::read_range("/foo/data.csv", 0, 1024).unwrap();
+/// ```
+pub fn read_range<P>(path: P, start: u64, len: u64) -> Result<Vec<u8>, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    match len {
+        0 => Ok(vec![]),
+        _ => fs.service.read_range(start, start.saturating_add(len - 1)),
+    }
+}
+
+/// Reads exactly `len` bytes of an S3 object, starting at byte `start`.
+///
+/// Unlike [read_range], returns `S3PathError::ShortRead` if the object ends before `start + len`.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let magic = fs::read_exact_range("/foo/archive.bin", 0, 16).unwrap();
+/// assert_eq!(magic.len(), 16);
+/// ```
+pub fn read_exact_range<P>(path: P, start: u64, len: u64) -> Result<Vec<u8>, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let contents = read_range(path, start, len)?;
+
+    match contents.len() as u64 == len {
+        true => Ok(contents),
+        false => Err(S3PathError::ShortRead),
+    }
+}
+
+/// Streams the lines of a text object, fetching it lazily in 1 MiB chunks rather than reading it
+/// into memory at once.
+///
+/// Lines are split on `\n`, with a trailing `\r` removed. Bytes that are not valid UTF-8 are
+/// replaced with `U+FFFD` so a single malformed line does not end the iteration. The iterator
+/// stops after the first request that fails, yielding its error.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// for line in fs::read_lines("/foo/events.ndjson").unwrap() {
+///     println!("{}", line.unwrap());
+/// }
+/// ```
+pub fn read_lines<P>(
+    path: P,
+) -> Result<impl Iterator<Item = Result<String, S3PathError>>, S3PathError>
 where
     P: ToString + Copy,
 {
     let fs = FS::from_string(path);
+    let mut reader = BufReader::new(S3Reader::new(fs.service)?);
+    let mut failed = false;
+
+    Ok(std::iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+
+        let mut line = vec![];
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with(b"\n") {
+                    line.pop();
+                    if line.ends_with(b"\r") {
+                        line.pop();
+                    }
+                }
+                Some(Ok(String::from_utf8_lossy(&line).into_owned()))
+            }
+            Err(e) => {
+                failed = true;
+                Some(Err(from_io_error(e)))
+            }
+        }
+    }))
+}
+
+/// Recovers the `S3PathError` wrapped by [S3Reader] from an `io::Error`.
+fn from_io_error(e: io::Error) -> S3PathError {
+    e.into_inner()
+        .and_then(|e| e.downcast::<S3PathError>().ok())
+        .map(|e| *e)
+        .unwrap_or(S3PathError::Unknown)
+}
 
-    dbg!(&fs.path.path.components());
+/// Writes `contents` to an S3 object, replacing it if it already exists.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// fs::write("/foo/bar.txt", "hello");
+/// ```
+pub fn write<P, C>(path: P, contents: C) -> Result<(), S3PathError>
+where
+    P: ToString + Copy,
+    C: AsRef<[u8]>,
+{
+    write_with_options(path, contents, &UploadOptions::default())
+}
 
-    // fs.create_dir(&fs.path)
+/// Writes `contents` to an S3 object using the headers configured in `options`.
+///
+/// Returns `S3PathError::InvalidRetention` without writing anything if `options` sets an
+/// Object Lock retention date that is not in the future.
+///
+/// A plain write drops the user metadata of the object it replaces; set
+/// [UploadOptions::preserve_metadata] to keep it.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::options::UploadOptions;
+/// let compressed: Vec<u8> = vec![];
+/// fs::write_with_options(
+///         "/foo/app.js",
+///         compressed,
+///         &UploadOptions::new().content_encoding("gzip"),
+///     );
+///
+/// fs::write_with_options("/foo/tagged.csv", "a,b\n", &UploadOptions::new().preserve_metadata(true));
+/// ```
+pub fn write_with_options<P, C>(
+    path: P,
+    contents: C,
+    options: &UploadOptions,
+) -> Result<(), S3PathError>
+where
+    P: ToString + Copy,
+    C: AsRef<[u8]>,
+{
+    let fs = FS::from_string(path);
+
+    fs.write(contents, options)
+}
+
+/// Writes a body of unknown length to an S3 object, replacing it if it already exists.
+///
+/// A body smaller than 100 MiB is buffered and written with a single request. Anything larger is
+/// streamed as a multipart upload in 8 MiB parts, which is aborted if any part fails. See
+/// [crate::options::TransferConfig] to change these sizes.
+///
+/// # Example
+///
+/// ```no_run
+/// use futures::stream::{self, StreamExt};
+/// use rusoto_s3::StreamingBody;
+/// use s3_fs::fs;
+/// use s3_fs::options::UploadOptions;
+/// let chunks = stream::iter(vec!["hello ", "world"]).map(|chunk| Ok(chunk.into()));
+/// let body = StreamingBody::new(chunks);
+/// fs::write_stream("/foo/bar.txt", body, &UploadOptions::default()).unwrap();
+/// ```
+pub fn write_stream<P>(
+    path: P,
+    body: StreamingBody,
+    options: &UploadOptions,
+) -> Result<(), S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.write_stream(body, options)
+}
+
+/// Downloads the S3 object at `path` to the local file `file`, returning the number of bytes written.
+///
+/// `progress` is called after every chunk received with the bytes received so far and the
+/// size of the object, when S3 reports it. The local file is created or truncated.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// fs::download_file("/foo/big.bin", "big.bin", |received, total| {
+///     if let Some(total) = total {
+///         println!("{}%", received * 100 / total.max(1));
+///     }
+/// })
+/// .unwrap();
+/// ```
+pub fn download_file<P, F>(
+    path: P,
+    file: impl AsRef<Path>,
+    mut progress: F,
+) -> Result<u64, S3PathError>
+where
+    P: ToString + Copy,
+    F: FnMut(u64, Option<u64>),
+{
+    let fs = FS::from_string(path);
+
+    fs.service.download_file(file.as_ref(), &mut progress)
+}
+
+/// Downloads the S3 object at `path` to the local file `file` as `parts` ranges fetched
+/// concurrently, returning the number of bytes written.
+///
+/// Each range is streamed straight to its offset in the file, so splitting a large object speeds
+/// up the transfer over high-latency links without holding it in memory. The local file is
+/// created or truncated.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let written = fs::download_file_parallel("/foo/big.bin", "big.bin", 8).unwrap();
+/// ```
+pub fn download_file_parallel<P>(
+    path: P,
+    file: impl AsRef<Path>,
+    parts: u64,
+) -> Result<u64, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.service.download_file_parallel(file.as_ref(), parts)
+}
+
+/// Uploads the local file `file` to the S3 object at `path`, returning the number of bytes sent.
+///
+/// Files smaller than 100 MiB are written with a single request, larger files as a multipart
+/// upload in 8 MiB parts. `progress` is called after every request with the bytes sent so far and
+/// the size of the file. See [S3Path::upload_file] to configure the transfer.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::options::UploadOptions;
+/// fs::upload_file("big.bin", "/foo/big.bin", &UploadOptions::default(), |sent, total| {
+///     println!("{} of {:?} bytes", sent, total);
+/// })
+/// .unwrap();
+/// ```
+pub fn upload_file<P, F>(
+    file: impl AsRef<Path>,
+    path: P,
+    options: &UploadOptions,
+    mut progress: F,
+) -> Result<u64, S3PathError>
+where
+    P: ToString + Copy,
+    F: FnMut(u64, Option<u64>),
+{
+    options.validate()?;
+    let fs = FS::from_string(path);
+
+    fs.service
+        .upload_file(file.as_ref(), options, &mut progress)
+}
+
+/// Streams everything `reader` yields to an S3 object, replacing it if it already exists, and
+/// returns the number of bytes written. The input is never buffered whole: as with
+/// [write_stream], anything past 100 MiB is sent as a multipart upload in 8 MiB parts.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::options::UploadOptions;
+/// use std::process::{Command, Stdio};
+/// let mut gzip = Command::new("gzip")
+///     .args(["-c", "export.csv"])
+///     .stdout(Stdio::piped())
+///     .spawn()
+///     .unwrap();
+/// let written = fs::write_from_reader(
+///     "/foo/export.csv.gz",
+///     gzip.stdout.take().unwrap(),
+///     &UploadOptions::new().content_encoding("gzip"),
+/// )
+/// .unwrap();
+/// ```
+pub fn write_from_reader<P, R>(
+    path: P,
+    mut reader: R,
+    options: &UploadOptions,
+) -> Result<u64, S3PathError>
+where
+    P: ToString + Copy,
+    R: Read,
+{
+    options.validate()?;
+    let fs = FS::from_string(path);
+
+    fs.service.upload_from_reader(&mut reader, options)
+}
+
+/// Writes `contents` to an S3 object along with an additional checksum computed with `algorithm`.
+///
+/// S3 rejects the upload if the content it received does not match the checksum, and stores the
+/// checksum so it can later be read back with [S3Path::checksum]. On success, it returns the
+/// base64 encoded checksum.
+///
+/// # Note
+/// Objects built from an `S3Client` with [S3Path::from_s3_client] fail with
+/// `S3PathError::UnknownClient`, build them with [S3Path::from_credentials] instead.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::checksum::ChecksumAlgorithm;
+/// use s3_fs::fs;
+/// let checksum = fs::write_with_checksum("/foo/bar.txt", "hello", ChecksumAlgorithm::Crc32c).unwrap();
+/// assert_eq!(checksum, ChecksumAlgorithm::Crc32c.compute(b"hello"));
+/// ```
+pub fn write_with_checksum<P, C>(
+    path: P,
+    contents: C,
+    algorithm: ChecksumAlgorithm,
+) -> Result<String, S3PathError>
+where
+    P: ToString + Copy,
+    C: AsRef<[u8]>,
+{
+    let fs = FS::from_string(path);
+
+    fs.write_with_checksum(contents, algorithm)
+}
+
+/// Overwrites the S3 object at `path` with `contents` only if its ETag is still `e_tag`,
+/// returning the ETag of the new contents.
+///
+/// If the object was changed by someone else since `e_tag` was read, nothing is written and
+/// `S3PathError::PreconditionFailed` is returned. This allows a safe read-modify-write cycle
+/// without any external locking.
+///
+/// # Note
+/// Objects built from an `S3Client` with [S3Path::from_s3_client] fail with
+/// `S3PathError::UnknownClient`, build them with [S3Path::from_credentials] instead.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::errors::S3PathError;
+/// use s3_fs::fs;
+/// use s3_fs::s3::S3Path;
+/// let e_tag = S3Path::new("/foo/config.json").metadata().unwrap().e_tag;
+/// match fs::write_if_match("/foo/config.json", "{\"retries\": 3}", &e_tag) {
+///     Ok(new_e_tag) => println!("updated to {}", new_e_tag),
+///     Err(S3PathError::PreconditionFailed) => println!("someone else changed it first"),
+///     Err(e) => panic!("{}", e),
+/// }
+/// ```
+pub fn write_if_match<P, C>(path: P, contents: C, e_tag: &ETag) -> Result<ETag, S3PathError>
+where
+    P: ToString + Copy,
+    C: AsRef<[u8]>,
+{
+    let fs = FS::from_string(path);
+
+    fs.write_if_match(contents, e_tag)
+}
+
+/// Writes `contents` to a new S3 object, returning `S3PathError::ObjectAlreadyExists`
+/// if an object is already stored at `path`.
+///
+/// The write is sent with `If-None-Match: *`, so S3 refuses it even if a concurrent writer
+/// created `path` in the meantime.
+///
+/// # Note
+/// Paths built with [S3Path::from_s3_client] are checked with a HEAD request before the write
+/// instead, so a concurrent writer may still create `path` between the check and the write.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// fs::write_no_overwrite("/foo/bar.txt", "hello");
+/// ```
+pub fn write_no_overwrite<P, C>(path: P, contents: C) -> Result<(), S3PathError>
+where
+    P: ToString + Copy,
+    C: AsRef<[u8]>,
+{
+    let fs = FS::from_string(path);
+
+    fs.write_no_overwrite(contents)
+}
+
+/// Checks whether each of `keys` exists in the bucket of `bucket_path`, returning a map from key to existence.
+///
+/// The HEAD requests are made concurrently, with a bounded number in flight, on a single runtime.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let keys = (0..500)
+///     .map(|part| format!("job/output/part-{:05}", part))
+///     .collect::<Vec<String>>();
+/// let existing = fs::objects_exist("/foo", &keys).unwrap();
+///
+/// assert!(existing.values().all(|exists| *exists));
+/// ```
+pub fn objects_exist<P>(
+    bucket_path: P,
+    keys: &[String],
+) -> Result<HashMap<String, bool>, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(bucket_path);
+
+    fs.objects_exist(keys)
+}
+
+/// Creates an empty object at `path` if nothing is stored there yet.
+///
+/// # Note
+/// Unlike the Unix `touch`, an existing object is left completely untouched: its contents,
+/// metadata and last-modified time are not changed.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// fs::touch("/foo/job/output/_SUCCESS");
+/// ```
+pub fn touch<P>(path: P) -> Result<(), S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.touch()
+}
+
+/// Creates a new directory in an s3 bucket, returning its `S3Path`, whose key ends with `/`.
+///
+/// The directory is marked by an empty object whose key ends with `/`. Like `std::fs::create_dir`,
+/// it returns `S3PathError::ObjectAlreadyExists` if that marker already exists; see
+/// [create_dir_all] to succeed instead.
+///
+/// # Note
+/// If you do not pass a full S3 path, the function splits the provided path with "/" and assumes the first part is the bucket.
+/// i.e The bucket name in "foo/bar/doo/dah" is "foo".
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let dir = fs::create_dir("/foo/some_dir").unwrap();
+///
+/// assert_eq!(dir.key(), "some_dir/");
+/// dir.try_exists();
+/// ```
+pub fn create_dir<P>(path: P) -> Result<S3Path, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.create_dir(false)
+}
+
+/// Recursively create a directory and all of its parent components if they are missing,
+/// returning the `S3Path` of the directory.
+///
+/// Like `std::fs::create_dir_all`, it is idempotent: markers that already exist are left as they
+/// are, so creating the same directory twice succeeds without writing anything the second time.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::s3::S3Path;
+/// fs::create_dir_all("/foo/some_dir/bar/").unwrap();
+/// let dir = fs::create_dir_all("/foo/some_dir/bar/").unwrap();
+///
+/// S3Path::new("/foo/some_dir/").try_exists();
+/// println!("{} entries", dir.read_dir().unwrap().files().len());
+/// ```
+pub fn create_dir_all<P>(path: P) -> Result<S3Path, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.create_dir_all()
+}
+
+/// Returns the metadata of every file directly under a directory, taken from the listing itself.
+///
+/// Listings already carry the size, ETag, modification date and storage class of each object,
+/// so no HEAD request is made per file: a directory of up to 1000 files takes a single request.
+/// The content-type and user metadata are not part of a listing and are left empty, and
+/// subdirectories are not included.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// for (path, metadata) in fs::stat_dir("/foo/reports/").unwrap() {
+///     println!("{}  {}", metadata, path.key());
+/// }
+/// ```
+pub fn stat_dir<P>(path: P) -> Result<Vec<(S3Path, ObjectMetadata)>, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.stat_dir()
+}
+
+/// Removes the marker object of a directory with a single DeleteObject request, without first
+/// listing the directory to check that it is empty.
+///
+/// Objects under the directory are not removed, so if there are any, the directory still
+/// appears in listings once its marker is gone. Only use it when the directory is known to be
+/// empty, or when its contents are meant to stay. Removing a marker that does not exist succeeds.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// fs::remove_dir_forced("/foo/staging/").unwrap();
+/// ```
+pub fn remove_dir_forced<P>(path: P) -> Result<(), S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.remove_dir_forced()
+}
+
+/// Permanently removes one version of an object in a versioned bucket, as listed by
+/// [list_versions], instead of hiding the object behind a delete marker.
+///
+/// The other versions are kept. Removing the latest version makes the one before it current,
+/// and removing a delete marker restores the object. Removing a missing version succeeds.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// for version in fs::list_versions("/foo/report.csv").unwrap() {
+///     if version.key == "report.csv" && version.size == Some(0) {
+///         fs::remove_version("/foo/report.csv", &version.version_id).unwrap();
+///     }
+/// }
+/// ```
+pub fn remove_version<P>(path: P, version_id: &str) -> Result<(), S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.remove_version(version_id)
+}
+
+/// Lists the keys of the subdirectories directly under a directory, such as `some_dir/bar/`.
+///
+/// Only the common prefixes of the listing are kept, so no `S3Path` is built for the objects
+/// it contains. As with [read_dir], `path` is always treated as a directory.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// for dir in fs::list_dirs("/foo/some_dir/").unwrap() {
+///     println!("{}", dir);
+/// }
+/// ```
+pub fn list_dirs<P>(path: P) -> Result<Vec<String>, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.list_service(&ListOptions::default()).list_dirs()
+}
+
+/// Lists the contents of a directory, returning the files and subdirectories directly under it.
+///
+/// `path` is always treated as a directory, so listing `/foo/dir` does not include the
+/// entries of a sibling such as `/foo/dir-backup/`. See [read_dir_with_options] to match the
+/// prefix as given instead. A path naming only the bucket, such as `/foo/`, lists the
+/// top-level entries of the bucket.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let listing = fs::read_dir("/foo/some_dir/").unwrap();
+/// let top_level = fs::read_dir("/foo/").unwrap();
+///
+/// for file in listing.files() {
+///     println!("{:?}", file);
+/// }
+/// for dir in listing.dirs() {
+///     println!("{:?}", dir);
+/// }
+/// ```
+pub fn read_dir<P>(path: P) -> Result<Listing, S3PathError>
+where
+    P: ToString + Copy,
+{
+    read_dir_with_options(path, &ListOptions::default())
+}
+
+/// Lists the contents of a directory as configured by `options`.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::options::ListOptions;
+/// let listing = fs::read_dir_with_options("/foo/logs-2024", &ListOptions::new().prefix_match(true)).unwrap();
+/// ```
+pub fn read_dir_with_options<P>(path: P, options: &ListOptions) -> Result<Listing, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.read_dir(options)
+}
+
+/// Returns the total size in bytes of every object under a directory, at any depth.
+///
+/// This is the S3 equivalent of `du -s`. Use [dir_usage] to also get the number of objects.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let bytes = fs::dir_size("/foo/dataset/").unwrap();
+/// ```
+pub fn dir_size<P>(path: P) -> Result<u64, S3PathError>
+where
+    P: ToString + Copy,
+{
+    Ok(dir_usage(path)?.bytes)
+}
+
+/// Returns the total size and the number of objects under a directory, at any depth.
+///
+/// Every page of the listing is fetched, so this takes one request per 1000 objects.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let usage = fs::dir_usage("/foo/dataset/").unwrap();
+///
+/// println!("{} bytes in {} objects", usage.bytes, usage.objects);
+/// ```
+pub fn dir_usage<P>(path: P) -> Result<DirUsage, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.dir_usage()
+}
+
+/// Lists the versions and delete markers of a versioned bucket whose keys start with `path`,
+/// ordered by key and then from the newest to the oldest. The versions of other keys sharing
+/// the prefix, such as `/foo/report.csv.bak` for `/foo/report.csv`, are listed as well.
+///
+/// Every page of the listing is fetched, so this takes one request per 1000 versions.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let versions = fs::list_versions("/foo/report.csv").unwrap();
+///
+/// for version in versions.iter().filter(|version| version.key == "report.csv") {
+///     println!("{} latest: {}", version.version_id, version.is_latest);
+/// }
+/// ```
+pub fn list_versions<P>(path: P) -> Result<Vec<ObjectVersion>, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.list_versions()
+}
+
+/// Removes every object under `prefix`, at any depth, whose key matches the glob `pattern`,
+/// returning the keys that were removed and those that could not be.
+///
+/// A pattern without a `/`, such as `*.tmp`, is matched against the file name of each object.
+/// A pattern with a `/` is matched against the key relative to `prefix`. `?` and `*` do not
+/// match `/`, while `**` does, so `logs/**/*.gz` matches every `.gz` file below `logs/`,
+/// including `logs/a.gz`.
+///
+/// Matching objects are deleted in batches of 1000 keys. A key or batch that fails does not
+/// stop the others; see [BatchResult::into_result] to treat any failure as an error.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let removed = fs::remove_matching("/foo/work/", "*.tmp").unwrap();
+///
+/// println!("removed {} temporary files", removed.succeeded.len());
+/// for (key, error) in removed.failed {
+///     println!("{} was not removed: {}", key, error);
+/// }
+/// ```
+pub fn remove_matching<P>(prefix: P, pattern: &str) -> Result<BatchResult, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(prefix);
+
+    fs.remove_matching(pattern)
+}
+
+/// Finds every object under `prefix`, at any depth, whose tag `key` is set to `value`.
+///
+/// S3 cannot filter a listing by tags, so the tags of each listed object are fetched with
+/// `GetObjectTagging`, 16 objects at a time.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// for path in fs::find_by_tag("/foo/jobs/", "status", "ready").unwrap() {
+///     println!("{}", path.key());
+/// }
+/// ```
+pub fn find_by_tag<P>(prefix: P, key: &str, value: &str) -> Result<Vec<S3Path>, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(prefix);
+
+    fs.find_by_tag(key, value)
+}
+
+/// Lists the contents of a directory lazily, fetching the next page of the listing only
+/// once the entries of the current page have been consumed.
+///
+/// Subdirectories are yielded before the files of each page.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+///
+/// for entry in fs::read_dir_stream("/foo/huge_dir/") {
+///     println!("{:?}", entry.unwrap());
+/// }
+/// ```
+pub fn read_dir_stream<P>(path: P) -> ReadDirStream
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.read_dir_stream(&ListOptions::default())
+}
+
+/// Creates a symbolic link at `link` pointing at `target`.
+///
+/// S3 has no native links, so the link is emulated with a zero-byte object whose
+/// `x-amz-website-redirect-location` is set to the target's key. The target must live in the same bucket as the link,
+/// otherwise `S3PathError::LinkAcrossBuckets` is returned.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::s3::S3Path;
+/// fs::soft_link(
+///         S3Path::new("/foo/v1.2.3"),
+///         "/foo/latest",
+///     );
+/// ```
+pub fn soft_link<P>(target: S3Path, link: P) -> Result<String, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(link);
+
+    fs.soft_link(&target)
+}
+
+/// Reads a symbolic link created by [soft_link], returning the path it points to.
+///
+/// Returns `S3PathError::NotALink` if the object has no redirect location.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// let target = fs::read_link("/foo/latest");
+/// ```
+pub fn read_link<P>(path: P) -> Result<S3Path, S3PathError>
+where
+    P: ToString + Copy,
+{
+    let fs = FS::from_string(path);
+
+    fs.read_link()
+}
+
+/// Mirrors every object under the `src` prefix into the `dst` prefix.
+///
+/// Objects missing from `dst`, or whose size or ETag differ from the source, are copied server-side.
+/// With [SyncOptions::delete], objects under `dst` that have no counterpart under `src` are removed.
+/// Running the same sync twice copies nothing the second time.
+///
+/// A key that cannot be copied or deleted does not stop the sync: it is listed in
+/// [SyncReport::failed], and the next sync tries it again.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::s3::S3Path;
+/// use s3_fs::sync::SyncOptions;
+/// let report = fs::sync(
+///         &S3Path::new("/foo/data/"),
+///         &S3Path::new("/backup/data/"),
+///         &SyncOptions::new().delete(true),
+///     )
+///     .unwrap();
+///
+/// println!("copied {:?}, deleted {:?}", report.copied, report.deleted);
+/// ```
+pub fn sync(src: &S3Path, dst: &S3Path, options: &SyncOptions) -> Result<SyncReport, S3PathError> {
+    sync::sync(&src.service, &dst.service, options)
+}
+
+/// Renames an object by copying it server-side to `to` and deleting `from`, returning whether
+/// `from` was removed. `to` can live in another bucket.
+///
+/// S3 has no atomic rename, so for a moment both objects exist, and a write to `from` in between
+/// is lost. The copy always completes before `from` is deleted, so no data is lost if the delete
+/// fails: with `keep_source_on_failure`, the rename then returns `Ok(false)` leaving both objects
+/// in place, otherwise it returns the error of the delete, also leaving both objects in place.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::s3::S3Path;
+/// let removed = fs::rename(S3Path::new("/foo/draft.txt"), &S3Path::new("/foo/final.txt"), true).unwrap();
+/// if !removed {
+///     println!("renamed, but /foo/draft.txt could not be deleted");
+/// }
+/// ```
+pub fn rename(
+    from: S3Path,
+    to: &S3Path,
+    keep_source_on_failure: bool,
+) -> Result<bool, S3PathError> {
+    let fs = FS::new(from);
+
+    fs.rename(to, keep_source_on_failure)
+}
+
+/// Moves every object under the `from` prefix to the `to` prefix, like renaming a directory.
+///
+/// Objects are copied server-side, concurrently, so nothing is downloaded. The sources are
+/// deleted only once all copies have been attempted, and only those that were copied. Keys that
+/// could not be moved are listed in [MoveReport::failed] and remain under `from`.
+///
+/// # Example
+///
+/// ```no_run
+/// use s3_fs::fs;
+/// use s3_fs::s3::S3Path;
+/// let report = fs::move_dir(S3Path::new("/foo/old/"), S3Path::new("/foo/new/")).unwrap();
+///
+/// for (key, error) in report.failed {
+///     println!("{} was not moved: {}", key, error);
+/// }
+/// ```
+pub fn move_dir(from: S3Path, to: S3Path) -> Result<MoveReport, S3PathError> {
+    sync::move_dir(&from.service, &to.service)
 }
@@ -0,0 +1,102 @@
+/// Returns `true` if `text` matches the glob `pattern`.
+///
+/// `?` matches any single character and `*` any run of characters, both stopping at `/`.
+/// `**` matches any run of characters, including `/`, and `**/` also matches no directory at
+/// all, so `**/*.tmp` matches `a.tmp` as well as `a/b/c.tmp`.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let text = text.chars().collect::<Vec<char>>();
+
+    Matcher {
+        pattern: &pattern,
+        text: &text,
+        memo: vec![None; (pattern.len() + 1) * (text.len() + 1)],
+    }
+    .matches(0, 0)
+}
+
+struct Matcher<'a> {
+    pattern: &'a [char],
+    text: &'a [char],
+    /// Whether `text[t..]` matches `pattern[p..]`, at `p * (text.len() + 1) + t`.
+    memo: Vec<Option<bool>>,
+}
+
+impl Matcher<'_> {
+    /// Each position pair is solved once, so patterns with many wildcards do not backtrack
+    /// exponentially.
+    fn matches(&mut self, p: usize, t: usize) -> bool {
+        let index = p * (self.text.len() + 1) + t;
+        if let Some(matched) = self.memo[index] {
+            return matched;
+        }
+
+        let matched = self.solve(p, t);
+        self.memo[index] = Some(matched);
+        matched
+    }
+
+    fn solve(&mut self, p: usize, t: usize) -> bool {
+        let text = self.text;
+        match &self.pattern[p..] {
+            [] => t == text.len(),
+            ['*', '*', '/', ..] if self.matches(p + 3, t) => true,
+            ['*', '*', ..] => (t..=text.len()).any(|i| self.matches(p + 2, i)),
+            ['*', ..] => (t..=text.len())
+                .take_while(|&i| i == t || text[i - 1] != '/')
+                .any(|i| self.matches(p + 1, i)),
+            ['?', ..] => t < text.len() && text[t] != '/' && self.matches(p + 1, t + 1),
+            [c, ..] => t < text.len() && text[t] == *c && self.matches(p + 1, t + 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_stops_at_slashes() {
+        assert!(matches("*.csv", "a.csv"));
+        assert!(matches("data/*.csv", "data/a.csv"));
+        assert!(!matches("*.csv", "data/a.csv"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(!matches("a?c", "a/c"));
+    }
+
+    #[test]
+    fn double_star_crosses_slashes() {
+        assert!(matches("**", "a/b/c"));
+        assert!(matches("a/**", "a/b/c.txt"));
+        assert!(matches("a/**/c.txt", "a/b/d/c.txt"));
+    }
+
+    #[test]
+    fn double_star_slash_matches_zero_directories() {
+        assert!(matches("**/*.tmp", "a.tmp"));
+        assert!(matches("**/*.tmp", "a/b/c.tmp"));
+        assert!(matches("a/**/c.txt", "a/c.txt"));
+        assert!(!matches("**/*.tmp", "a.txt"));
+    }
+
+    #[test]
+    fn literals_must_match_exactly() {
+        assert!(matches("a/b.txt", "a/b.txt"));
+        assert!(!matches("a/b.txt", "a/b.txt2"));
+        assert!(!matches("a/b.txt", "a/b.tx"));
+    }
+
+    #[test]
+    fn many_wildcards_do_not_backtrack_exponentially() {
+        let pattern = "**a**a**a**a**a**a**a**a**a**a**b";
+        let text = "a".repeat(200);
+
+        assert!(!matches(pattern, &text));
+    }
+}
@@ -0,0 +1,413 @@
+use crate::errors::S3PathError;
+
+const MAX_BITS: usize = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+/// Decompresses a gzip stream made of one or more members, verifying each member's CRC32.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, S3PathError> {
+    let mut reader = BitReader::new(data);
+    let mut out = vec![];
+
+    while reader.pos < data.len() {
+        let start = out.len();
+        read_header(&mut reader)?;
+        inflate(&mut reader, &mut out)?;
+
+        let crc = reader.u32_le()?;
+        let size = reader.u32_le()?;
+        let member = &out[start..];
+        if crc32fast::hash(member) != crc || member.len() as u32 != size {
+            return Err(S3PathError::DecompressionFailed);
+        }
+    }
+
+    Ok(out)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            pos: 0,
+            bit_buf: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn bits(&mut self, need: u32) -> Result<u32, S3PathError> {
+        let mut value = self.bit_buf;
+        while self.bit_count < need {
+            value |= (self.byte()? as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+
+        self.bit_buf = value >> need;
+        self.bit_count -= need;
+
+        Ok(value & ((1 << need) - 1))
+    }
+
+    fn align(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+
+    fn byte(&mut self) -> Result<u8, S3PathError> {
+        let byte = *self
+            .data
+            .get(self.pos)
+            .ok_or(S3PathError::DecompressionFailed)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn u16_le(&mut self) -> Result<u16, S3PathError> {
+        Ok(self.byte()? as u16 | (self.byte()? as u16) << 8)
+    }
+
+    fn u32_le(&mut self) -> Result<u32, S3PathError> {
+        Ok(self.u16_le()? as u32 | (self.u16_le()? as u32) << 16)
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), S3PathError> {
+        if self.pos + count > self.data.len() {
+            return Err(S3PathError::DecompressionFailed);
+        }
+        self.pos += count;
+        Ok(())
+    }
+
+    fn skip_zero_terminated(&mut self) -> Result<(), S3PathError> {
+        while self.byte()? != 0 {}
+        Ok(())
+    }
+}
+
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, S3PathError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(S3PathError::DecompressionFailed);
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, S3PathError> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+
+        for length in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - count < first {
+                // An incomplete table leaves some codes without a symbol.
+                return usize::try_from(index + code - first)
+                    .ok()
+                    .and_then(|index| self.symbols.get(index).copied())
+                    .ok_or(S3PathError::DecompressionFailed);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(S3PathError::DecompressionFailed)
+    }
+}
+
+fn read_header(reader: &mut BitReader) -> Result<(), S3PathError> {
+    if reader.byte()? != 0x1f || reader.byte()? != 0x8b || reader.byte()? != 8 {
+        return Err(S3PathError::DecompressionFailed);
+    }
+
+    let flags = reader.byte()?;
+    // MTIME, XFL and OS carry nothing needed for decoding.
+    reader.skip(6)?;
+
+    if flags & FEXTRA != 0 {
+        let length = reader.u16_le()? as usize;
+        reader.skip(length)?;
+    }
+    if flags & FNAME != 0 {
+        reader.skip_zero_terminated()?;
+    }
+    if flags & FCOMMENT != 0 {
+        reader.skip_zero_terminated()?;
+    }
+    if flags & FHCRC != 0 {
+        reader.skip(2)?;
+    }
+
+    Ok(())
+}
+
+fn inflate(reader: &mut BitReader, out: &mut Vec<u8>) -> Result<(), S3PathError> {
+    loop {
+        let last = reader.bits(1)?;
+        match reader.bits(2)? {
+            0 => stored(reader, out)?,
+            1 => {
+                let (lengths, distances) = fixed_tables()?;
+                codes(reader, out, &lengths, &distances)?
+            }
+            2 => {
+                let (lengths, distances) = dynamic_tables(reader)?;
+                codes(reader, out, &lengths, &distances)?
+            }
+            _ => return Err(S3PathError::DecompressionFailed),
+        }
+
+        if last == 1 {
+            reader.align();
+            return Ok(());
+        }
+    }
+}
+
+fn stored(reader: &mut BitReader, out: &mut Vec<u8>) -> Result<(), S3PathError> {
+    reader.align();
+
+    let length = reader.u16_le()?;
+    if reader.u16_le()? != !length {
+        return Err(S3PathError::DecompressionFailed);
+    }
+
+    let start = reader.pos;
+    reader.skip(length as usize)?;
+    out.extend_from_slice(&reader.data[start..reader.pos]);
+
+    Ok(())
+}
+
+fn fixed_tables() -> Result<(Huffman, Huffman), S3PathError> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5u8; 30])?))
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman), S3PathError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_count] {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+
+    let total = literal_count + distance_count;
+    let mut lengths = vec![0u8; total];
+    let mut index = 0;
+    while index < total {
+        let symbol = code_lengths.decode(reader)?;
+        if symbol < 16 {
+            lengths[index] = symbol as u8;
+            index += 1;
+            continue;
+        }
+
+        let (length, repeat) = match symbol {
+            16 if index > 0 => (lengths[index - 1], 3 + reader.bits(2)?),
+            17 => (0, 3 + reader.bits(3)?),
+            18 => (0, 11 + reader.bits(7)?),
+            _ => return Err(S3PathError::DecompressionFailed),
+        };
+        let end = index + repeat as usize;
+        if end > total {
+            return Err(S3PathError::DecompressionFailed);
+        }
+        lengths[index..end].fill(length);
+        index = end;
+    }
+
+    if lengths[256] == 0 {
+        return Err(S3PathError::DecompressionFailed);
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+fn codes(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    lengths: &Huffman,
+    distances: &Huffman,
+) -> Result<(), S3PathError> {
+    loop {
+        let symbol = lengths.decode(reader)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let symbol = symbol - 257;
+                if symbol >= LENGTH_BASE.len() {
+                    return Err(S3PathError::DecompressionFailed);
+                }
+                let length = LENGTH_BASE[symbol] as usize
+                    + reader.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+                let symbol = distances.decode(reader)? as usize;
+                if symbol >= DISTANCE_BASE.len() {
+                    return Err(S3PathError::DecompressionFailed);
+                }
+                let distance = DISTANCE_BASE[symbol] as usize
+                    + reader.bits(DISTANCE_EXTRA[symbol] as u32)? as usize;
+                if distance > out.len() {
+                    return Err(S3PathError::DecompressionFailed);
+                }
+
+                let start = out.len() - distance;
+                for offset in 0..length {
+                    out.push(out[start + offset]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `hello` in a stored block.
+    const STORED: [u8; 28] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x03, 0x01, 0x05, 0x00, 0xfa, 0xff,
+        0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x86, 0xa6, 0x10, 0x36, 0x05, 0x00, 0x00, 0x00,
+    ];
+    /// `hello hello hello` with fixed Huffman codes and back-references.
+    const FIXED: [u8; 28] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9,
+        0x57, 0xc8, 0x40, 0x90, 0x00, 0x80, 0x88, 0xf9, 0xe5, 0x11, 0x00, 0x00, 0x00,
+    ];
+    /// [DYNAMIC_CONTENT] with dynamic Huffman codes.
+    const DYNAMIC: [u8; 44] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x25, 0xc8, 0x81, 0x09, 0x00,
+        0x00, 0x0c, 0xc2, 0xb0, 0x5b, 0xe3, 0xff, 0x47, 0x0c, 0x3a, 0x11, 0xc4, 0x6c, 0xc5, 0x68,
+        0xaa, 0x4e, 0x64, 0x39, 0x7b, 0x74, 0xaf, 0xbb, 0x28, 0x65, 0x31, 0x00, 0x00, 0x00,
+    ];
+    const DYNAMIC_CONTENT: &[u8] = b"bbbbbbbabaabbbabbabbaaaabbbbaabbababbbaaabaaabbba";
+    /// A dynamic block whose code length code gives all 19 symbols a length of 1.
+    const OVERSUBSCRIBED: [u8; 28] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x03, 0x05, 0xe0, 0x93, 0x24, 0x49,
+        0x92, 0x24, 0x49, 0x92, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn decompresses_every_block_type() {
+        assert_eq!(decompress(&STORED).unwrap(), b"hello");
+        assert_eq!(decompress(&FIXED).unwrap(), b"hello hello hello");
+        assert_eq!(decompress(&DYNAMIC).unwrap(), DYNAMIC_CONTENT);
+    }
+
+    #[test]
+    fn concatenates_members() {
+        let data = [&STORED[..], &FIXED[..]].concat();
+
+        assert_eq!(decompress(&data).unwrap(), b"hellohello hello hello");
+    }
+
+    #[test]
+    fn rejects_a_wrong_checksum() {
+        let mut data = STORED;
+        data[20] ^= 1;
+
+        assert_eq!(decompress(&data), Err(S3PathError::DecompressionFailed));
+    }
+
+    #[test]
+    fn rejects_an_oversubscribed_table() {
+        assert_eq!(
+            decompress(&OVERSUBSCRIBED),
+            Err(S3PathError::DecompressionFailed)
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        for length in 1..DYNAMIC.len() {
+            assert_eq!(
+                decompress(&DYNAMIC[..length]),
+                Err(S3PathError::DecompressionFailed)
+            );
+        }
+    }
+
+    #[test]
+    fn never_panics_on_corrupt_data() {
+        for data in [&STORED[..], &FIXED[..], &DYNAMIC[..]] {
+            for index in 10..data.len() {
+                for bit in 0..8 {
+                    let mut data = data.to_vec();
+                    data[index] ^= 1 << bit;
+                    let _ = decompress(&data);
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,19 @@
+pub mod acl;
 pub mod bucket;
+pub mod checksum;
+mod credentials;
 pub mod errors;
+pub mod etag;
 pub mod fs;
-mod object;
+mod glob;
+mod gzip;
+pub mod listing;
+pub mod object;
+pub mod options;
+pub mod reader;
 pub mod s3;
+mod select;
 mod services;
+pub mod sync;
+#[cfg(feature = "tracing")]
+mod trace;
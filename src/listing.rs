@@ -0,0 +1,194 @@
+use crate::errors::S3PathError;
+use crate::etag::ETag;
+use crate::s3::S3Path;
+use crate::services::S3Service;
+use std::collections::{HashMap, VecDeque};
+
+/// The result of listing a prefix, split into the objects directly under it and its subdirectories.
+#[derive(Debug)]
+pub struct Listing {
+    files: Vec<S3Path>,
+    dirs: Vec<S3Path>,
+    prefix: String,
+    owners: HashMap<String, ObjectOwner>,
+}
+
+/// The account owning an object, as returned by listings made with
+/// [crate::options::ListOptions::fetch_owner].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectOwner {
+    pub id: Option<String>,
+    pub display_name: Option<String>,
+}
+
+/// A version of an object, or a delete marker, as listed by [crate::fs::list_versions].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectVersion {
+    pub key: String,
+    pub version_id: String,
+    /// Whether this is the current version of the key.
+    pub is_latest: bool,
+    /// A delete marker hides the key without removing its older versions. It has no size or ETag.
+    pub is_delete_marker: bool,
+    pub last_modified: Option<String>,
+    pub size: Option<i64>,
+    pub e_tag: Option<ETag>,
+}
+
+impl Listing {
+    pub(crate) fn new(files: Vec<S3Path>, dirs: Vec<S3Path>, prefix: String) -> Self {
+        Listing {
+            files,
+            dirs,
+            prefix,
+            owners: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn with_owners(mut self, owners: HashMap<String, ObjectOwner>) -> Self {
+        self.owners = owners;
+        self
+    }
+
+    pub(crate) fn extend(&mut self, other: Listing) {
+        self.files.extend(other.files);
+        self.dirs.extend(other.dirs);
+        self.owners.extend(other.owners);
+    }
+
+    /// The objects directly under the listed prefix.
+    pub fn files(&self) -> &[S3Path] {
+        &self.files
+    }
+
+    /// The subdirectories (common prefixes) directly under the listed prefix.
+    pub fn dirs(&self) -> &[S3Path] {
+        &self.dirs
+    }
+
+    /// Consumes the listing, keeping only the objects directly under the listed prefix.
+    ///
+    /// Files and directories come from separate parts of the list response,
+    /// so no further request is made to tell them apart.
+    /// ```no_run
+    ///   use s3_fs::fs;
+    ///   let files = fs::read_dir("/foo/some_dir/").unwrap().files_only();
+    ///```
+    pub fn files_only(self) -> Vec<S3Path> {
+        self.files
+    }
+
+    /// Consumes the listing, keeping only the subdirectories directly under the listed prefix.
+    /// ```no_run
+    ///   use s3_fs::fs;
+    ///   for dir in fs::read_dir("/foo/some_dir/").unwrap().dirs_only() {
+    ///       println!("{:?}", dir);
+    ///   }
+    ///```
+    pub fn dirs_only(self) -> Vec<S3Path> {
+        self.dirs
+    }
+
+    /// The owner of `file`, if the listing was made with
+    /// [crate::options::ListOptions::fetch_owner] and `file` is one of its objects.
+    /// ```no_run
+    ///   use s3_fs::fs;
+    ///   use s3_fs::options::ListOptions;
+    ///   let listing = fs::read_dir_with_options("/foo/shared/", &ListOptions::new().fetch_owner(true)).unwrap();
+    ///   for file in listing.files() {
+    ///       println!("{} {:?}", file.key(), listing.owner(file).and_then(|owner| owner.display_name.as_deref()));
+    ///   }
+    ///```
+    pub fn owner(&self, file: &S3Path) -> Option<&ObjectOwner> {
+        self.owners.get(file.key())
+    }
+
+    /// The prefix that was listed.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// The key of `entry` relative to the listed prefix, e.g. `x/file.csv` for `data/x/file.csv`
+    /// when `data/` was listed. Keys outside the prefix are returned whole.
+    /// ```no_run
+    ///   use s3_fs::fs;
+    ///   let listing = fs::read_dir("/foo/data/").unwrap();
+    ///   for file in listing.files() {
+    ///       println!("{}", listing.relative_key(file));
+    ///   }
+    ///```
+    pub fn relative_key<'a>(&self, entry: &'a S3Path) -> &'a str {
+        entry
+            .key()
+            .strip_prefix(self.prefix.as_str())
+            .unwrap_or(entry.key())
+    }
+
+    /// The keys of the objects and then of the subdirectories of the listing, relative to the
+    /// listed prefix. See [Listing::relative_key].
+    pub fn relative_keys(&self) -> Vec<&str> {
+        self.files
+            .iter()
+            .chain(&self.dirs)
+            .map(|entry| self.relative_key(entry))
+            .collect()
+    }
+}
+
+/// The total size of the objects stored under a prefix, as computed by [crate::fs::dir_usage].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DirUsage {
+    /// The sum of the `content_length` of every object under the prefix.
+    pub bytes: u64,
+    /// The number of objects under the prefix, at any depth.
+    pub objects: u64,
+}
+
+/// An iterator over the entries of a directory that fetches one page of the listing at a time.
+///
+/// The next page is only requested once the entries of the current one have been consumed,
+/// so memory stays bounded to a single page.
+#[derive(Debug)]
+pub struct ReadDirStream {
+    service: S3Service,
+    entries: VecDeque<S3Path>,
+    continuation_token: Option<String>,
+    done: bool,
+}
+
+impl ReadDirStream {
+    pub(crate) fn new(service: S3Service) -> Self {
+        ReadDirStream {
+            service,
+            entries: VecDeque::new(),
+            continuation_token: None,
+            done: false,
+        }
+    }
+
+    fn fetch_page(&mut self) -> Result<(), S3PathError> {
+        let (page, next_token) = self.service.list_page(self.continuation_token.take())?;
+
+        self.entries.extend(page.dirs);
+        self.entries.extend(page.files);
+        self.done = next_token.is_none();
+        self.continuation_token = next_token;
+
+        Ok(())
+    }
+}
+
+impl Iterator for ReadDirStream {
+    type Item = Result<S3Path, S3PathError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.entries.is_empty() && !self.done {
+            if let Err(e) = self.fetch_page() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+
+        self.entries.pop_front().map(Ok)
+    }
+}
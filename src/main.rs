@@ -1,5 +1,5 @@
 use s3_fs::fs;
 
 fn main() {
-    fs::create_dir_all("/foo/bar");
+    fs::create_dir_all("/foo/bar").unwrap();
 }
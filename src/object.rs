@@ -1,5 +1,8 @@
+use crate::etag::ETag;
+use crate::options::DIRECTORY_CONTENT_TYPE;
+use chrono::DateTime;
 use std::collections::HashMap;
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum S3ObjectType {
@@ -7,23 +10,322 @@ pub enum S3ObjectType {
     Directory,
 }
 
+#[derive(Clone)]
 pub struct ObjectMetadata {
     pub content_type: String,
     pub content_length: Option<i64>,
-    pub e_tag: String,
+    pub e_tag: ETag,
     pub last_modified: String,
     pub metadata: Option<HashMap<String, String>>,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub content_encoding: Option<String>,
+    /// The `Expires` header of the object, as an HTTP date.
+    pub expires: Option<String>,
+    /// The storage class of the object, e.g. `GLACIER`. S3 omits it for `STANDARD` objects.
+    pub storage_class: Option<String>,
+    /// The raw `x-amz-restore` header of an archived object, see [ObjectMetadata::restore_status].
+    pub restore: Option<String>,
+    /// The server-side encryption of the object, e.g. `aws:kms`. Only known from a HEAD request.
+    pub server_side_encryption: Option<String>,
+}
+
+/// Where an archived object is in the restore process.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RestoreStatus {
+    /// No restore was requested, or the object is not archived.
+    NotRequested,
+    /// A restore was requested and the object cannot be read yet.
+    InProgress,
+    /// A temporary copy can be read until `expiry_date`.
+    Restored { expiry_date: Option<String> },
+}
+
+/// One difference between the metadata of two objects, as found by [ObjectMetadata::diff].
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetadataChange {
+    Size {
+        from: Option<i64>,
+        to: Option<i64>,
+    },
+    ETag {
+        from: ETag,
+        to: ETag,
+    },
+    ContentType {
+        from: String,
+        to: String,
+    },
+    LastModified {
+        from: String,
+        to: String,
+    },
+    /// A user metadata key only the other object has.
+    MetadataAdded {
+        key: String,
+        value: String,
+    },
+    /// A user metadata key only this object has.
+    MetadataRemoved {
+        key: String,
+        value: String,
+    },
+    /// A user metadata key both objects have, with different values.
+    MetadataChanged {
+        key: String,
+        from: String,
+        to: String,
+    },
 }
 
 impl ObjectMetadata {
+    /// Lists what differs from `self` to `other`: size, ETag, content-type, modification date
+    /// and user metadata, one change per key. An empty result means they match on all of them.
+    /// ```
+    ///   use s3_fs::object::MetadataChange;
+    ///   # use s3_fs::object::ObjectMetadata;
+    ///   # use std::collections::HashMap;
+    ///   # let metadata = |length: i64, author: &str| ObjectMetadata {
+    ///   #     content_type: "text/csv".to_string(), content_length: Some(length), e_tag: "\"abc\"".into(),
+    ///   #     last_modified: "Fri, 21 Dec 2012 00:00:00 GMT".to_string(),
+    ///   #     metadata: Some(HashMap::from([("author".to_string(), author.to_string())])),
+    ///   #     cache_control: None, content_disposition: None, content_encoding: None, expires: None, storage_class: None,
+    ///   #     restore: None, server_side_encryption: None,
+    ///   # };
+    ///   let changes = metadata(5, "me").diff(&metadata(7, "you"));
+    ///   assert_eq!(changes, vec![
+    ///       MetadataChange::Size { from: Some(5), to: Some(7) },
+    ///       MetadataChange::MetadataChanged { key: "author".to_string(), from: "me".to_string(), to: "you".to_string() },
+    ///   ]);
+    ///   assert!(metadata(5, "me").diff(&metadata(5, "me")).is_empty());
+    ///```
+    pub fn diff(&self, other: &ObjectMetadata) -> Vec<MetadataChange> {
+        let mut changes = vec![];
+
+        if self.content_length != other.content_length {
+            changes.push(MetadataChange::Size {
+                from: self.content_length,
+                to: other.content_length,
+            });
+        }
+        if self.e_tag != other.e_tag {
+            changes.push(MetadataChange::ETag {
+                from: self.e_tag.clone(),
+                to: other.e_tag.clone(),
+            });
+        }
+        if self.content_type != other.content_type {
+            changes.push(MetadataChange::ContentType {
+                from: self.content_type.clone(),
+                to: other.content_type.clone(),
+            });
+        }
+        if self.last_modified != other.last_modified {
+            changes.push(MetadataChange::LastModified {
+                from: self.last_modified.clone(),
+                to: other.last_modified.clone(),
+            });
+        }
+
+        let empty = HashMap::new();
+        let from = self.metadata.as_ref().unwrap_or(&empty);
+        let to = other.metadata.as_ref().unwrap_or(&empty);
+        let mut keys = from.keys().chain(to.keys()).collect::<Vec<&String>>();
+        keys.sort();
+        keys.dedup();
+
+        for key in keys {
+            let key = key.to_string();
+            match (from.get(&key), to.get(&key)) {
+                (None, Some(value)) => changes.push(MetadataChange::MetadataAdded {
+                    key,
+                    value: value.clone(),
+                }),
+                (Some(value), None) => changes.push(MetadataChange::MetadataRemoved {
+                    key,
+                    value: value.clone(),
+                }),
+                (Some(from), Some(to)) if from != to => {
+                    changes.push(MetadataChange::MetadataChanged {
+                        key,
+                        from: from.clone(),
+                        to: to.clone(),
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        changes
+    }
+
+    /// Parses the `x-amz-restore` header of the object.
+    /// ```
+    ///   use s3_fs::object::RestoreStatus;
+    ///   # use s3_fs::object::ObjectMetadata;
+    ///   # let metadata = |restore: Option<&str>| ObjectMetadata {
+    ///   #     content_type: String::new(), content_length: None, e_tag: "".into(),
+    ///   #     last_modified: String::new(), metadata: None, cache_control: None,
+    ///   #     content_disposition: None, content_encoding: None, expires: None,
+    ///   #     storage_class: Some("GLACIER".to_string()),
+    ///   #     restore: restore.map(str::to_string), server_side_encryption: None,
+    ///   # };
+    ///   assert_eq!(metadata(Some("ongoing-request=\"true\"")).restore_status(), RestoreStatus::InProgress);
+    ///   assert_eq!(
+    ///       metadata(Some("ongoing-request=\"false\", expiry-date=\"Fri, 21 Dec 2012 00:00:00 GMT\"")).restore_status(),
+    ///       RestoreStatus::Restored { expiry_date: Some("Fri, 21 Dec 2012 00:00:00 GMT".to_string()) }
+    ///   );
+    ///```
+    pub fn restore_status(&self) -> RestoreStatus {
+        let restore = match &self.restore {
+            Some(restore) => restore,
+            None => return RestoreStatus::NotRequested,
+        };
+
+        match restore.contains("ongoing-request=\"true\"") {
+            true => RestoreStatus::InProgress,
+            false => RestoreStatus::Restored {
+                expiry_date: restore
+                    .split_once("expiry-date=\"")
+                    .and_then(|(_, date)| date.split('"').next())
+                    .map(str::to_string),
+            },
+        }
+    }
+
+    /// Renders the metadata as the HTTP headers S3 returns with the object, e.g. to forward them
+    /// from a proxy re-serving it. Header names are lowercase, user metadata is prefixed with
+    /// `x-amz-meta-`, and headers the object does not have are left out.
+    /// ```
+    ///   # use s3_fs::object::ObjectMetadata;
+    ///   # use std::collections::HashMap;
+    ///   # let metadata = ObjectMetadata {
+    ///   #     content_type: "text/csv".to_string(), content_length: Some(5), e_tag: "\"abc\"".into(),
+    ///   #     last_modified: "Fri, 21 Dec 2012 00:00:00 GMT".to_string(),
+    ///   #     metadata: Some(HashMap::from([("author".to_string(), "me".to_string())])),
+    ///   #     cache_control: None, content_disposition: None, content_encoding: None, expires: None, storage_class: None,
+    ///   #     restore: None, server_side_encryption: None,
+    ///   # };
+    ///   let headers = metadata.as_headers();
+    ///   assert_eq!(headers["content-type"], "text/csv");
+    ///   assert_eq!(headers["content-length"], "5");
+    ///   assert_eq!(headers["etag"], "\"abc\"");
+    ///   assert_eq!(headers["x-amz-meta-author"], "me");
+    ///   assert!(!headers.contains_key("cache-control"));
+    ///```
+    pub fn as_headers(&self) -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+        let mut insert = |name: &str, value: Option<&str>| {
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                headers.insert(name.to_string(), value.to_string());
+            }
+        };
+
+        insert("content-type", Some(&self.content_type));
+        insert(
+            "content-length",
+            self.content_length
+                .map(|length| length.to_string())
+                .as_deref(),
+        );
+        insert("etag", Some(self.e_tag.as_str()));
+        insert("last-modified", Some(&self.last_modified));
+        insert("cache-control", self.cache_control.as_deref());
+        insert("content-disposition", self.content_disposition.as_deref());
+        insert("content-encoding", self.content_encoding.as_deref());
+        insert("expires", self.expires.as_deref());
+        insert("x-amz-storage-class", self.storage_class.as_deref());
+        insert("x-amz-restore", self.restore.as_deref());
+        insert(
+            "x-amz-server-side-encryption",
+            self.server_side_encryption.as_deref(),
+        );
+        for (key, value) in self.metadata.iter().flatten() {
+            insert(&format!("x-amz-meta-{}", key), Some(value));
+        }
+
+        headers
+    }
+
+    /// The hex encoded MD5 of the contents, if the ETag is one. It is not for objects uploaded
+    /// in parts, nor for objects encrypted with SSE-KMS.
+    /// ```
+    ///   # use s3_fs::object::ObjectMetadata;
+    ///   # let metadata = |encryption: &str| ObjectMetadata {
+    ///   #     content_type: String::new(), content_length: None,
+    ///   #     e_tag: "\"5d41402abc4b2a76b9719d911017c592\"".into(), last_modified: String::new(),
+    ///   #     metadata: None, cache_control: None, content_disposition: None, content_encoding: None,
+    ///   #     expires: None, storage_class: None, restore: None,
+    ///   #     server_side_encryption: Some(encryption.to_string()),
+    ///   # };
+    ///   assert_eq!(metadata("AES256").md5(), Some("5d41402abc4b2a76b9719d911017c592"));
+    ///   assert_eq!(metadata("aws:kms").md5(), None);
+    ///```
+    pub fn md5(&self) -> Option<&str> {
+        match &self.server_side_encryption {
+            Some(encryption) if encryption.starts_with("aws:kms") => None,
+            _ => self.e_tag.md5(),
+        }
+    }
+
     pub fn content_type(&self) -> S3ObjectType {
-        match self.content_type.contains("application/x-directory") {
+        match self.content_type.contains(DIRECTORY_CONTENT_TYPE) {
             true => S3ObjectType::Directory,
             false => S3ObjectType::File,
         }
     }
 }
 
+impl Display for S3ObjectType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            S3ObjectType::File => write!(f, "file"),
+            S3ObjectType::Directory => write!(f, "directory"),
+        }
+    }
+}
+
+/// Renders a byte count with binary units, e.g. `1.2 MiB`.
+fn human_size(bytes: i64) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Prints a single listing-style line: size, content-type, modification date and object type.
+impl Display for ObjectMetadata {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let size = match self.content_length {
+            Some(length) => human_size(length),
+            None => "-".to_string(),
+        };
+        let modified = match DateTime::parse_from_rfc2822(&self.last_modified) {
+            Ok(date) => date.format("%Y-%m-%d").to_string(),
+            Err(_) => self.last_modified.to_string(),
+        };
+
+        write!(
+            f,
+            "{}  {}  {}  {}",
+            size,
+            self.content_type,
+            modified,
+            self.content_type()
+        )
+    }
+}
+
 impl Debug for ObjectMetadata {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("S3Object")
@@ -32,6 +334,13 @@ impl Debug for ObjectMetadata {
             .field("e_tag", &self.e_tag)
             .field("last_modified", &self.last_modified)
             .field("metadata", &self.metadata)
+            .field("cache_control", &self.cache_control)
+            .field("content_disposition", &self.content_disposition)
+            .field("content_encoding", &self.content_encoding)
+            .field("expires", &self.expires)
+            .field("storage_class", &self.storage_class)
+            .field("restore", &self.restore)
+            .field("server_side_encryption", &self.server_side_encryption)
             .finish()
     }
 }
@@ -0,0 +1,679 @@
+use crate::errors::S3PathError;
+use crate::object::ObjectMetadata;
+use chrono::{DateTime, SecondsFormat, Utc};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::collections::HashMap;
+
+/// Options applied to the objects written by the upload functions in [crate::fs].
+///
+/// ```
+///   use s3_fs::options::UploadOptions;
+///   let options = UploadOptions::new().content_encoding("gzip");
+///   assert_eq!(options.get_content_encoding(), Some("gzip"));
+///```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UploadOptions {
+    content_encoding: Option<String>,
+    cache_control: Option<String>,
+    content_disposition: Option<String>,
+    expires: Option<String>,
+    object_lock: Option<ObjectLock>,
+    verify_etag: bool,
+    preserve_metadata: bool,
+    metadata: Option<HashMap<String, String>>,
+    pub(crate) content_type: Option<String>,
+    pub(crate) content_md5: Option<String>,
+}
+
+impl UploadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the `Content-Encoding` header of the object, e.g. `gzip` for a body that was compressed before upload.
+    pub fn content_encoding(mut self, encoding: &str) -> Self {
+        self.content_encoding = Some(encoding.to_string());
+        self
+    }
+
+    /// Set the `Cache-Control` header of the object, e.g. `max-age=3600`, returned to browsers and CDNs.
+    pub fn cache_control(mut self, cache_control: &str) -> Self {
+        self.cache_control = Some(cache_control.to_string());
+        self
+    }
+
+    /// Set the `Content-Disposition` header of the object, e.g. `attachment; filename="report.csv"`
+    /// to make browsers download it under that name.
+    pub fn content_disposition(mut self, content_disposition: &str) -> Self {
+        self.content_disposition = Some(content_disposition.to_string());
+        self
+    }
+
+    /// Set the `Expires` header of the object, after which caches consider it stale.
+    /// Accepts a `DateTime<Utc>` or a `SystemTime`.
+    ///
+    /// ```
+    ///   use chrono::{TimeZone, Utc};
+    ///   use s3_fs::options::UploadOptions;
+    ///   let options = UploadOptions::new().expires(Utc.with_ymd_and_hms(2035, 1, 1, 0, 0, 0).unwrap());
+    ///   assert_eq!(options.get_expires(), Some("Mon, 01 Jan 2035 00:00:00 GMT"));
+    ///```
+    pub fn expires<T: Into<DateTime<Utc>>>(mut self, expires: T) -> Self {
+        self.expires = Some(
+            expires
+                .into()
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string(),
+        );
+        self
+    }
+
+    /// Protect the object with S3 Object Lock. The bucket must have Object Lock enabled.
+    pub fn object_lock(mut self, object_lock: ObjectLock) -> Self {
+        self.object_lock = Some(object_lock);
+        self
+    }
+
+    /// Compute the expected ETag of the object while it is uploaded and fail with
+    /// `S3PathError::ChecksumMismatch` if S3 returns a different one.
+    ///
+    /// Objects encrypted with SSE-KMS or SSE-C have ETags that are not derived from their
+    /// contents, so this must stay off when writing to buckets that use them.
+    pub fn verify_etag(mut self, verify_etag: bool) -> Self {
+        self.verify_etag = verify_etag;
+        self
+    }
+
+    /// Carry the user metadata (`x-amz-meta-*` headers) of the object being replaced over to
+    /// the new one, at the cost of a HEAD request before the upload. Writing a missing object
+    /// is not an error. Keys set with [UploadOptions::metadata] replace the carried ones.
+    ///
+    /// ```
+    ///   use s3_fs::options::UploadOptions;
+    ///   let options = UploadOptions::new().preserve_metadata(true);
+    ///   assert!(options.get_preserve_metadata());
+    ///```
+    pub fn preserve_metadata(mut self, preserve_metadata: bool) -> Self {
+        self.preserve_metadata = preserve_metadata;
+        self
+    }
+
+    /// Set user metadata on the object, sent as `x-amz-meta-*` headers. With
+    /// [UploadOptions::preserve_metadata], the keys are merged over those of the object being
+    /// replaced.
+    ///
+    /// The upload fails with `S3PathError::InvalidMetadata` if a key is not a valid header name
+    /// or a value is not printable ASCII.
+    ///
+    /// ```
+    ///   use s3_fs::options::UploadOptions;
+    ///   use std::collections::HashMap;
+    ///   let metadata = HashMap::from([("reviewed".to_string(), "yes".to_string())]);
+    ///   let options = UploadOptions::new().preserve_metadata(true).metadata(metadata);
+    ///   assert_eq!(options.get_metadata().unwrap()["reviewed"], "yes");
+    ///```
+    pub fn metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn get_content_encoding(&self) -> Option<&str> {
+        self.content_encoding.as_deref()
+    }
+
+    pub fn get_cache_control(&self) -> Option<&str> {
+        self.cache_control.as_deref()
+    }
+
+    pub fn get_content_disposition(&self) -> Option<&str> {
+        self.content_disposition.as_deref()
+    }
+
+    /// The `Expires` header, formatted as an HTTP date.
+    pub fn get_expires(&self) -> Option<&str> {
+        self.expires.as_deref()
+    }
+
+    pub fn get_object_lock(&self) -> Option<&ObjectLock> {
+        self.object_lock.as_ref()
+    }
+
+    pub fn get_verify_etag(&self) -> bool {
+        self.verify_etag
+    }
+
+    pub fn get_preserve_metadata(&self) -> bool {
+        self.preserve_metadata
+    }
+
+    pub fn get_metadata(&self) -> Option<&HashMap<String, String>> {
+        self.metadata.as_ref()
+    }
+
+    /// The options that carry the content-type and web headers of `metadata` over to a copy of
+    /// its object, as `CopyObject` does.
+    pub(crate) fn preserving(metadata: &ObjectMetadata) -> Self {
+        UploadOptions {
+            content_type: Some(metadata.content_type.to_string()).filter(|t| !t.is_empty()),
+            content_encoding: metadata.content_encoding.clone(),
+            cache_control: metadata.cache_control.clone(),
+            content_disposition: metadata.content_disposition.clone(),
+            expires: metadata.expires.clone(),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), S3PathError> {
+        if let Some(metadata) = &self.metadata {
+            validate_metadata(metadata)?;
+        }
+
+        match &self.object_lock {
+            Some(lock) if lock.retain_until <= Utc::now() => Err(S3PathError::InvalidRetention),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The retention mode of an object protected by S3 Object Lock.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectLockMode {
+    /// Users with the `s3:BypassGovernanceRetention` permission can still delete the object.
+    Governance,
+    /// Nobody, including the root user, can delete the object until its retention expires.
+    Compliance,
+}
+
+/// A write-once-read-many retention applied to an uploaded object.
+///
+/// ```
+///   use chrono::{Duration, Utc};
+///   use s3_fs::options::{ObjectLock, ObjectLockMode, UploadOptions};
+///   let seven_years = Utc::now() + Duration::days(7 * 365);
+///   let options = UploadOptions::new()
+///       .object_lock(ObjectLock::new(ObjectLockMode::Compliance, seven_years));
+///   assert_eq!(options.get_object_lock().unwrap().mode, ObjectLockMode::Compliance);
+///```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectLock {
+    pub mode: ObjectLockMode,
+    /// The object cannot be overwritten or deleted before this date, which must be in the future.
+    pub retain_until: DateTime<Utc>,
+    /// Also place a legal hold, which blocks deletion regardless of the retention date.
+    pub legal_hold: bool,
+}
+
+impl ObjectLock {
+    pub fn new(mode: ObjectLockMode, retain_until: DateTime<Utc>) -> Self {
+        ObjectLock {
+            mode,
+            retain_until,
+            legal_hold: false,
+        }
+    }
+
+    pub fn legal_hold(mut self, legal_hold: bool) -> Self {
+        self.legal_hold = legal_hold;
+        self
+    }
+
+    pub(crate) fn mode_header(&self) -> String {
+        match self.mode {
+            ObjectLockMode::Governance => "GOVERNANCE".to_string(),
+            ObjectLockMode::Compliance => "COMPLIANCE".to_string(),
+        }
+    }
+
+    pub(crate) fn retain_until_header(&self) -> String {
+        self.retain_until.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+
+    pub(crate) fn legal_hold_header(&self) -> String {
+        match self.legal_hold {
+            true => "ON".to_string(),
+            false => "OFF".to_string(),
+        }
+    }
+}
+
+/// Checks that user metadata can be sent as `x-amz-meta-*` headers: keys must be non-empty
+/// HTTP header tokens and values printable ASCII.
+pub(crate) fn validate_metadata(metadata: &HashMap<String, String>) -> Result<(), S3PathError> {
+    let valid_key = |key: &String| {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+    };
+    let valid_value = |value: &String| value.chars().all(|c| c == ' ' || c.is_ascii_graphic());
+
+    match metadata
+        .iter()
+        .all(|(key, value)| valid_key(key) && valid_value(value))
+    {
+        true => Ok(()),
+        false => Err(S3PathError::InvalidMetadata),
+    }
+}
+
+/// Headers S3 returns in place of the object's own when it is read, e.g. to force a browser to
+/// download an object stored without a `Content-Disposition`.
+///
+/// ```
+///   use s3_fs::options::ResponseOverrides;
+///   let overrides = ResponseOverrides::new()
+///       .content_type("text/csv")
+///       .content_disposition("attachment; filename=\"report.csv\"");
+///   assert_eq!(overrides.get_content_type(), Some("text/csv"));
+///```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseOverrides {
+    content_type: Option<String>,
+    content_disposition: Option<String>,
+    content_encoding: Option<String>,
+    content_language: Option<String>,
+    cache_control: Option<String>,
+    expires: Option<String>,
+}
+
+impl ResponseOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+
+    pub fn content_disposition(mut self, content_disposition: &str) -> Self {
+        self.content_disposition = Some(content_disposition.to_string());
+        self
+    }
+
+    pub fn content_encoding(mut self, content_encoding: &str) -> Self {
+        self.content_encoding = Some(content_encoding.to_string());
+        self
+    }
+
+    pub fn content_language(mut self, content_language: &str) -> Self {
+        self.content_language = Some(content_language.to_string());
+        self
+    }
+
+    pub fn cache_control(mut self, cache_control: &str) -> Self {
+        self.cache_control = Some(cache_control.to_string());
+        self
+    }
+
+    /// Accepts a `DateTime<Utc>` or a `SystemTime`, sent as an HTTP date.
+    pub fn expires<T: Into<DateTime<Utc>>>(mut self, expires: T) -> Self {
+        self.expires = Some(
+            expires
+                .into()
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string(),
+        );
+        self
+    }
+
+    pub fn get_content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn get_content_disposition(&self) -> Option<&str> {
+        self.content_disposition.as_deref()
+    }
+
+    pub fn get_content_encoding(&self) -> Option<&str> {
+        self.content_encoding.as_deref()
+    }
+
+    pub fn get_content_language(&self) -> Option<&str> {
+        self.content_language.as_deref()
+    }
+
+    pub fn get_cache_control(&self) -> Option<&str> {
+        self.cache_control.as_deref()
+    }
+
+    pub fn get_expires(&self) -> Option<&str> {
+        self.expires.as_deref()
+    }
+}
+
+/// The smallest part S3 accepts in a multipart upload, other than the last one.
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// How large transfers are split into parts and how many parts are in flight at once.
+///
+/// Applies to the multipart uploads, parallel downloads and multipart copies made through an
+/// [crate::s3::S3Path] configured with [crate::s3::S3Path::with_transfer_config]. An upload holds
+/// up to `concurrency` parts of `part_size` bytes in memory.
+///
+/// ```
+///   use s3_fs::options::TransferConfig;
+///   let config = TransferConfig::new().part_size(64 * 1024 * 1024).concurrency(32);
+///   assert_eq!(config.get_part_size(), 64 * 1024 * 1024);
+///   assert_eq!(config.get_multipart_threshold(), 100 * 1024 * 1024);
+///```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferConfig {
+    part_size: usize,
+    concurrency: usize,
+    multipart_threshold: usize,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        TransferConfig {
+            part_size: 8 * 1024 * 1024,
+            concurrency: 10,
+            multipart_threshold: 100 * 1024 * 1024,
+        }
+    }
+}
+
+impl TransferConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The size of each part, raised to S3's minimum of 5 MiB. Uploads of a known size and
+    /// copies use larger parts when needed to stay within S3's limit of 10,000 parts.
+    pub fn part_size(mut self, part_size: usize) -> Self {
+        self.part_size = part_size.max(MIN_PART_SIZE);
+        self
+    }
+
+    /// The number of parts transferred at the same time, at least 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Bodies of at least this many bytes are uploaded in parts; smaller ones are buffered and
+    /// sent with a single request.
+    pub fn multipart_threshold(mut self, multipart_threshold: usize) -> Self {
+        self.multipart_threshold = multipart_threshold.max(1);
+        self
+    }
+
+    pub fn get_part_size(&self) -> usize {
+        self.part_size
+    }
+
+    pub fn get_concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn get_multipart_threshold(&self) -> usize {
+        self.multipart_threshold
+    }
+}
+
+/// Options controlling how [crate::fs::read_dir_with_options] lists a prefix.
+///
+/// ```
+///   use s3_fs::options::ListOptions;
+///   let options = ListOptions::new().prefix_match(true).max_keys(100);
+///```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListOptions {
+    prefix_match: bool,
+    max_keys: Option<i64>,
+    recursive: bool,
+    fetch_owner: bool,
+}
+
+impl ListOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// List every key starting with the path as given, instead of only the entries of the
+    /// directory. With it, listing `/bucket/dir` also returns `/bucket/dir-backup/`.
+    pub fn prefix_match(mut self, prefix_match: bool) -> Self {
+        self.prefix_match = prefix_match;
+        self
+    }
+
+    /// The number of keys requested per page, up to S3's limit of 1000.
+    pub fn max_keys(mut self, max_keys: i64) -> Self {
+        self.max_keys = Some(max_keys);
+        self
+    }
+
+    /// List every object under the prefix at any depth, as a flat list of files without
+    /// subdirectories, instead of only the entries directly under it.
+    ///
+    /// ```
+    ///   use s3_fs::options::ListOptions;
+    ///   let options = ListOptions::new().recursive(true);
+    ///   assert!(options.get_recursive());
+    ///```
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Ask S3 for the owner of each object, available from [crate::listing::Listing::owner].
+    pub fn fetch_owner(mut self, fetch_owner: bool) -> Self {
+        self.fetch_owner = fetch_owner;
+        self
+    }
+
+    pub fn get_prefix_match(&self) -> bool {
+        self.prefix_match
+    }
+
+    pub fn get_max_keys(&self) -> Option<i64> {
+        self.max_keys
+    }
+
+    pub fn get_recursive(&self) -> bool {
+        self.recursive
+    }
+
+    pub fn get_fetch_owner(&self) -> bool {
+        self.fetch_owner
+    }
+}
+
+/// How fast an archived object is restored, from the quickest and most expensive to the
+/// slowest and cheapest. `Expedited` is not available for `DEEP_ARCHIVE`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tier {
+    Expedited,
+    Standard,
+    Bulk,
+}
+
+impl Tier {
+    pub(crate) fn as_header(&self) -> &str {
+        match self {
+            Tier::Expedited => "Expedited",
+            Tier::Standard => "Standard",
+            Tier::Bulk => "Bulk",
+        }
+    }
+}
+
+/// How a server-side copy treats the tags of the source object.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaggingDirective {
+    /// Carry the source object's tags over to the copy. This is what S3 does by default.
+    Copy,
+    /// Tag the copy with these tags only. An empty map leaves the copy untagged.
+    Replace(HashMap<String, String>),
+}
+
+/// The characters escaped in a tag set sent as URL query parameters.
+const TAG_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+impl TaggingDirective {
+    pub(crate) fn as_header(&self) -> &str {
+        match self {
+            TaggingDirective::Copy => "COPY",
+            TaggingDirective::Replace(_) => "REPLACE",
+        }
+    }
+
+    /// The `x-amz-tagging` header of the replacing tags, as URL query parameters.
+    ///
+    /// ```
+    ///   use s3_fs::options::TaggingDirective;
+    ///   use std::collections::HashMap;
+    ///   let tags = HashMap::from([("stage".to_string(), "processed".to_string()), ("team".to_string(), "data eng".to_string())]);
+    ///   assert_eq!(TaggingDirective::Replace(tags).tagging(), Some("stage=processed&team=data%20eng".to_string()));
+    ///   assert_eq!(TaggingDirective::Copy.tagging(), None);
+    ///```
+    pub fn tagging(&self) -> Option<String> {
+        let tags = match self {
+            TaggingDirective::Copy => return None,
+            TaggingDirective::Replace(tags) => tags,
+        };
+
+        let mut pairs = tags
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "{}={}",
+                    utf8_percent_encode(key, TAG_SET),
+                    utf8_percent_encode(value, TAG_SET)
+                )
+            })
+            .collect::<Vec<String>>();
+        pairs.sort();
+
+        Some(pairs.join("&"))
+    }
+}
+
+/// How a server-side copy treats the metadata of the source object.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataDirective {
+    /// Carry the source object's content-type and user metadata over to the copy.
+    Copy,
+    /// Replace the content-type and user metadata of the copy.
+    ///
+    /// As with S3 itself, replacing with an empty `metadata` map clears all user metadata,
+    /// and a `None` content-type falls back to S3's default.
+    Replace {
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+    },
+}
+
+impl MetadataDirective {
+    pub(crate) fn as_header(&self) -> &str {
+        match self {
+            MetadataDirective::Copy => "COPY",
+            MetadataDirective::Replace { .. } => "REPLACE",
+        }
+    }
+}
+
+/// How directories are marked in a bucket. S3 has no directories, so tools emulate them with
+/// marker objects, each in their own way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DirectoryMarker {
+    /// A zero-byte object whose key ends with `/`, like the folders of the AWS console.
+    #[default]
+    TrailingSlash,
+    /// A zero-byte object named after the directory with a `_$folder$` suffix, as written by
+    /// Hadoop and EMR.
+    HadoopSuffix,
+    /// No marker at all: a directory exists only while objects live under it.
+    None,
+}
+
+const HADOOP_SUFFIX: &str = "_$folder$";
+
+/// The content-type given to directory markers by default, which the AWS console and most
+/// tools recognise as a folder.
+pub const DIRECTORY_CONTENT_TYPE: &str = "application/x-directory";
+
+impl DirectoryMarker {
+    /// The key of the object marking the directory `key`, with or without its trailing `/`.
+    ///
+    /// ```
+    ///   use s3_fs::options::DirectoryMarker;
+    ///   assert_eq!(DirectoryMarker::TrailingSlash.marker_key("logs"), Some("logs/".to_string()));
+    ///   assert_eq!(DirectoryMarker::HadoopSuffix.marker_key("logs/"), Some("logs_$folder$".to_string()));
+    ///   assert_eq!(DirectoryMarker::None.marker_key("logs/"), None);
+    /// ```
+    pub fn marker_key(&self, key: &str) -> Option<String> {
+        let key = key.trim_end_matches('/');
+        match self {
+            DirectoryMarker::TrailingSlash => Some(format!("{}/", key)),
+            DirectoryMarker::HadoopSuffix => Some(format!("{}{}", key, HADOOP_SUFFIX)),
+            DirectoryMarker::None => None,
+        }
+    }
+
+    /// The directory, ending with `/`, that the object `key` marks, if it is a marker.
+    pub(crate) fn directory_of(&self, key: &str) -> Option<String> {
+        match self {
+            DirectoryMarker::TrailingSlash => key.ends_with('/').then(|| key.to_string()),
+            DirectoryMarker::HadoopSuffix => key
+                .strip_suffix(HADOOP_SUFFIX)
+                .map(|directory| format!("{}/", directory)),
+            DirectoryMarker::None => None,
+        }
+    }
+}
+
+/// The format of an object queried with S3 Select.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectInput {
+    /// Comma-separated values. With `has_header`, the first line names the columns, which the
+    /// expression can then refer to, e.g. `s.status`.
+    Csv {
+        has_header: bool,
+    },
+    /// One JSON document per line.
+    JsonLines,
+    /// A single JSON document.
+    JsonDocument,
+    Parquet,
+}
+
+impl SelectInput {
+    pub(crate) fn as_xml(&self) -> String {
+        match self {
+            SelectInput::Csv { has_header } => format!(
+                "<CSV><FileHeaderInfo>{}</FileHeaderInfo></CSV>",
+                match has_header {
+                    true => "USE",
+                    false => "NONE",
+                }
+            ),
+            SelectInput::JsonLines => "<JSON><Type>LINES</Type></JSON>".to_string(),
+            SelectInput::JsonDocument => "<JSON><Type>DOCUMENT</Type></JSON>".to_string(),
+            SelectInput::Parquet => "<Parquet></Parquet>".to_string(),
+        }
+    }
+}
+
+/// The format S3 Select returns the matching records in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectOutput {
+    /// Comma-separated values, one record per line.
+    Csv,
+    /// One JSON object per line.
+    Json,
+}
+
+impl SelectOutput {
+    pub(crate) fn as_xml(&self) -> &str {
+        match self {
+            SelectOutput::Csv => "<CSV></CSV>",
+            SelectOutput::Json => "<JSON></JSON>",
+        }
+    }
+}
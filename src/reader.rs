@@ -0,0 +1,99 @@
+use crate::errors::S3PathError;
+use crate::services::S3Service;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// The number of bytes fetched by each ranged GET.
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// A reader over an S3 object implementing `std::io::Read` and `std::io::Seek`.
+///
+/// Bytes are fetched lazily with ranged GET requests of 1 MiB around the current position,
+/// so seeking is free and only the parts of the object that are read are downloaded.
+/// The length of the object is read once, when the reader is created.
+#[derive(Debug)]
+pub struct S3Reader {
+    service: S3Service,
+    length: u64,
+    position: u64,
+    buffer: Vec<u8>,
+    buffer_start: u64,
+}
+
+impl S3Reader {
+    pub(crate) fn new(service: S3Service) -> Result<Self, S3PathError> {
+        let length = service
+            .get_object_metadata()?
+            .content_length
+            .unwrap_or_default() as u64;
+
+        Ok(S3Reader {
+            service,
+            length,
+            position: 0,
+            buffer: vec![],
+            buffer_start: 0,
+        })
+    }
+
+    /// The length of the object in bytes.
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    fn buffered(&self) -> bool {
+        self.position >= self.buffer_start
+            && self.position < self.buffer_start + self.buffer.len() as u64
+    }
+}
+
+impl Read for S3Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.length || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if !self.buffered() {
+            let end = (self.position + CHUNK_SIZE).min(self.length) - 1;
+            self.buffer = self
+                .service
+                .read_range(self.position, end)
+                .map_err(io::Error::from)?;
+            self.buffer_start = self.position;
+            if self.buffer.is_empty() {
+                return Ok(0);
+            }
+        }
+
+        let offset = (self.position - self.buffer_start) as usize;
+        let count = buf.len().min(self.buffer.len() - offset);
+        buf[..count].copy_from_slice(&self.buffer[offset..offset + count]);
+        self.position += count as u64;
+
+        Ok(count)
+    }
+}
+
+impl Seek for S3Reader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
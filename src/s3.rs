@@ -1,16 +1,39 @@
+use crate::acl::Acl;
 use crate::bucket::BucketConfig;
+use crate::checksum::ChecksumAlgorithm;
 use crate::errors::S3PathError;
+use crate::listing::Listing;
 use crate::object::{ObjectMetadata, S3ObjectType};
+use crate::options::{
+    self, DirectoryMarker, ResponseOverrides, SelectInput, SelectOutput, Tier, TransferConfig,
+    UploadOptions,
+};
+use crate::reader::S3Reader;
 use crate::services::S3Service;
-use rusoto_s3::S3Client;
+use crate::sync;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use rusoto_core::credential::ProvideAwsCredentials;
+use rusoto_core::Region;
+use rusoto_s3::{S3Client, StreamingBody};
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
+use std::io::Write;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The number of bytes of each object read at a time by [S3Path::content_equals].
+const COMPARE_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
 
 pub struct S3Path {
     /// A `PathBuf` object representing the path.
     pub path: PathBuf,
     /// A [ObjectContent] representation of the content of the path.
-    service: S3Service,
+    pub(crate) service: S3Service,
+    /// The metadata of the object, once fetched, when caching is enabled.
+    metadata_cache: Option<Mutex<Option<ObjectMetadata>>>,
 }
 
 impl Debug for S3Path {
@@ -19,9 +42,60 @@ impl Debug for S3Path {
     }
 }
 
+/// Two paths are equal when they name the same key in the same bucket, however they were written.
+///
+/// ```
+///   use s3_fs::s3::S3Path;
+///   assert_eq!(S3Path::new("/foo//dir/"), S3Path::new("s3://foo/dir/"));
+///   assert_ne!(S3Path::new("/foo/dir/"), S3Path::new("/foo/dir"));
+///```
+impl PartialEq for S3Path {
+    fn eq(&self, other: &Self) -> bool {
+        self.service.bucket == other.service.bucket
+    }
+}
+
+/// Converts an absolute local-style path such as `/bucket/dir/key`, so a `PathBuf` built with
+/// `push` can be used as an `S3Path`. Unlike [S3Path::new], which panics, a relative path, one
+/// without a bucket or one that is not valid UTF-8 is returned as `S3PathError::InvalidPath`.
+///
+/// ```
+///   use s3_fs::errors::S3PathError;
+///   use s3_fs::s3::S3Path;
+///   use std::path::{Path, PathBuf};
+///   let mut path = PathBuf::from("/foo");
+///   path.push("reports");
+///   path.push("summary.csv");
+///   assert_eq!(S3Path::try_from(path).unwrap(), S3Path::new("/foo/reports/summary.csv"));
+///   assert_eq!(S3Path::try_from(Path::new("foo/bar.txt")), Err(S3PathError::InvalidPath));
+///```
+impl TryFrom<&Path> for S3Path {
+    type Error = S3PathError;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        match path.to_str() {
+            Some(path) if path.starts_with('/') && path.split('/').any(|part| !part.is_empty()) => {
+                Ok(S3Path::new(path))
+            }
+            _ => Err(S3PathError::InvalidPath),
+        }
+    }
+}
+
+impl TryFrom<PathBuf> for S3Path {
+    type Error = S3PathError;
+
+    fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
+        S3Path::try_from(path.as_path())
+    }
+}
+
 impl S3Path {
     /// Create a new S3Path from an absolute path.
     /// This will create a new rusoto S3 client first (see ) and use the client for making requests.
+    ///
+    /// The path is normalized as described in [BucketConfig::from_path]: repeated slashes are
+    /// collapsed and a trailing slash marks a directory.
     /// # Examples
     /// ```
     ///   use s3_fs::s3::S3Path;
@@ -35,14 +109,18 @@ impl S3Path {
         let service = S3Service::new(path.to_string());
         let path = Self::clean_path(path);
 
-        S3Path { path, service }
+        S3Path {
+            path,
+            service,
+            metadata_cache: None,
+        }
     }
 
     /// Create an S3Path from an S3 client and use the client for making requests.
     /// # Examples
     /// ```no_run
     ///
-    ///   use rusoto_s3::S3Client;
+    ///   use rusoto_s3::{S3Client, StreamingBody};
     ///   use rusoto_core::region::Region;
     ///   use s3_fs::s3::S3Path;
     ///   let s3_client = S3Client::new(Region::UsEast1);
@@ -51,12 +129,120 @@ impl S3Path {
     ///```
     pub fn from_s3_client<P: ToString + Copy>(path: P, s3_client: S3Client) -> Self {
         let service = S3Service::from_client(path.to_string(), s3_client);
+        let path = Self::clean_path(path);
 
-        let path = PathBuf::from(path.to_string());
+        S3Path {
+            path,
+            service,
+            metadata_cache: None,
+        }
+    }
 
-        Self::validate_path(&path);
+    /// Create an S3Path whose requests are signed with `credentials` and sent to `region`.
+    ///
+    /// Unlike [S3Path::from_s3_client], the credentials and region are also used for the
+    /// requests this crate signs by hand, such as [crate::fs::write_if_match], and for the URLs
+    /// of [S3Path::presigned_get].
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use rusoto_core::region::Region;
+    ///   use rusoto_core::credential::StaticProvider;
+    ///   use s3_fs::s3::S3Path;
+    ///   let provider = StaticProvider::new_minimal("key".to_string(), "secret".to_string());
+    ///   let s3_path = S3Path::from_credentials("/foo/bar.txt", provider, Region::EuWest1);
+    ///
+    ///```
+    pub fn from_credentials<P, C>(path: P, credentials: C, region: Region) -> Self
+    where
+        P: ToString + Copy,
+        C: ProvideAwsCredentials + Send + Sync + 'static,
+    {
+        let service = S3Service::from_credentials(path.to_string(), credentials, region);
+        let path = Self::clean_path(path);
+
+        S3Path {
+            path,
+            service,
+            metadata_cache: None,
+        }
+    }
+
+    /// Create an S3Path whose requests are sent unsigned, without looking up any credentials.
+    /// This reads public buckets and objects, such as open datasets, from machines with no AWS
+    /// credentials configured. Private objects answer with an error.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::anonymous("/noaa-ghcn-pds/readme.txt");
+    ///   s3_path.exists();
+    ///
+    ///```
+    pub fn anonymous<P: ToString + Copy>(path: P) -> Self {
+        let service = S3Service::anonymous(path.to_string());
+        let path = Self::clean_path(path);
 
-        S3Path { path, service }
+        S3Path {
+            path,
+            service,
+            metadata_cache: None,
+        }
+    }
+
+    /// Create an S3Path whose client uses temporary credentials obtained by assuming `role_arn`
+    /// through STS. The credentials are refreshed automatically once they expire.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::with_assumed_role(
+    ///       "/foo/bar.txt",
+    ///       "arn:aws:iam::123456789012:role/data-lake",
+    ///       "s3-fs-session",
+    ///   );
+    ///
+    ///```
+    pub fn with_assumed_role<P: ToString + Copy>(
+        path: P,
+        role_arn: &str,
+        session_name: &str,
+    ) -> Self {
+        let service = S3Service::from_assumed_role(
+            path.to_string(),
+            role_arn.to_string(),
+            session_name.to_string(),
+        );
+        let path = Self::clean_path(path);
+
+        S3Path {
+            path,
+            service,
+            metadata_cache: None,
+        }
+    }
+
+    /// Create an S3Path from an S3 URL such as one copied from the AWS console.
+    /// When the host names a region, requests are sent to that region.
+    /// See [BucketConfig::from_uri] for the accepted forms.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::from_uri("https://foo.s3.us-west-2.amazonaws.com/bar.txt").unwrap();
+    ///   s3_path.exists();
+    ///
+    ///```
+    pub fn from_uri(uri: &str) -> Result<Self, S3PathError> {
+        let (bucket, region) = BucketConfig::from_uri(uri)?;
+        let path = format!("/{}/{}", bucket.name, bucket.key);
+        let service = S3Service::in_region(path.to_string(), region.unwrap_or_default());
+
+        Ok(S3Path {
+            path: PathBuf::from(path),
+            service,
+            metadata_cache: None,
+        })
     }
 
     /// Create a new S3Path from a `BucketConfig`.
@@ -72,21 +258,270 @@ impl S3Path {
     ///
     ///```
     pub fn from_bucket(bucket: BucketConfig) -> Self {
-        let path = format!("/{}/{}", bucket.name, bucket.key);
+        Self::from_service(S3Service::from_bucket(bucket))
+    }
 
-        Self::new(&path)
+    /// Create a new S3Path for `key` in `bucket`, taken as they are. Unlike [S3Path::new],
+    /// no path is parsed or normalized, so a key whose slashes matter is kept intact.
+    /// # Examples
+    /// ```
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::from_parts("foo", "reports/2024/summary.csv");
+    ///   assert_eq!(s3_path.bucket(), "foo");
+    ///   assert_eq!(s3_path.key(), "reports/2024/summary.csv");
+    ///
+    ///```
+    pub fn from_parts(bucket: &str, key: &str) -> Self {
+        Self::from_bucket(BucketConfig {
+            name: bucket.to_string(),
+            key: key.to_string(),
+        })
     }
 
-    /// Returns `true` if the object exists
+    /// The deepest directory containing every one of `paths`, as a path like `/bucket/dir/` that
+    /// [S3Path::new] accepts. Only whole directories are shared, so `data/a.csv` and `data/ab.csv`
+    /// give `/bucket/data/`. Returns `None` if `paths` is empty or spans more than one bucket.
+    /// # Examples
+    /// ```
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let paths = [
+    ///       S3Path::from_parts("foo", "data/2024/01/a.csv"),
+    ///       S3Path::from_parts("foo", "data/2024/02/b.csv"),
+    ///   ];
+    ///   assert_eq!(S3Path::common_prefix(&paths), Some("/foo/data/2024/".to_string()));
+    ///   assert_eq!(S3Path::common_prefix(&[S3Path::from_parts("foo", "a"), S3Path::from_parts("bar", "a")]), None);
+    ///
+    ///```
+    pub fn common_prefix(paths: &[S3Path]) -> Option<String> {
+        let (first, rest) = paths.split_first()?;
+        if rest.iter().any(|path| path.bucket() != first.bucket()) {
+            return None;
+        }
+
+        let mut shared = first.key();
+        for path in rest {
+            let length = shared
+                .char_indices()
+                .zip(path.key().chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(shared.len().min(path.key().len()), |((index, _), _)| index);
+            shared = &shared[..length];
+        }
+        // A key shared up to its end is still only contained in its parent directory.
+        let dir = match shared.rfind('/') {
+            Some(index) => &shared[..=index],
+            None => "",
+        };
+
+        Some(format!("/{}/{}", first.bucket(), dir))
+    }
+
+    /// Mark the requests made through this path as paid for by the requester.
+    /// This is required to read from requester-pays buckets.
     /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/public-dataset/data.csv").requester_pays();
+    ///   s3_path.exists();
+    ///
     ///```
-    /// use s3_fs::s3::S3Path;
-    /// let s3_path = S3Path::new("/foo/bar");
-    /// assert_eq!(s3_path.exists(), false)
+    pub fn requester_pays(mut self) -> Self {
+        self.service.requester_pays = true;
+        self
+    }
+
+    /// Pin the requests made through this path to buckets owned by `account_id`.
+    /// S3 rejects the request with a 403 if the bucket belongs to another account.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/bar.txt").expect_owner("123456789012");
+    ///   s3_path.exists();
     ///
+    ///```
+    pub fn expect_owner(mut self, account_id: &str) -> Self {
+        self.service.expected_owner = Some(account_id.to_string());
+        self
+    }
+
+    /// Returns the `S3Client` this path makes its requests with, configured with the same
+    /// credentials and region. Use it to call operations this crate does not wrap.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use rusoto_s3::{GetBucketVersioningRequest, S3};
+    ///   use s3_fs::s3::S3Path;
+    ///
+    ///   # async fn run() {
+    ///   let s3_path = S3Path::new("/foo/bar.txt");
+    ///   let request = GetBucketVersioningRequest {
+    ///       bucket: "foo".to_string(),
+    ///       ..Default::default()
+    ///   };
+    ///   let versioning = s3_path.client().get_bucket_versioning(request).await.unwrap();
+    ///   # }
+    ///```
+    pub fn client(&self) -> &S3Client {
+        self.service.client()
+    }
+
+    /// The name of the bucket the path lives in.
+    /// # Examples
     /// ```
-    pub fn exists(&self) -> bool {
-        self.service.object_exists().is_ok()
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("s3://foo/some_dir/bar.txt");
+    ///   assert_eq!(s3_path.bucket(), "foo");
+    ///   assert_eq!(s3_path.key(), "some_dir/bar.txt");
+    ///
+    ///```
+    pub fn bucket(&self) -> &str {
+        &self.service.bucket.name
+    }
+
+    /// The key of the object within its bucket, without a leading slash. Directories keep
+    /// their trailing slash.
+    pub fn key(&self) -> &str {
+        &self.service.bucket.key
+    }
+
+    /// Send the requests made through this path to the region the bucket actually lives in.
+    ///
+    /// The bucket's region is looked up once, with a HEAD request on the bucket, and the client
+    /// is rebuilt for it if it differs from the configured region. Without it, requests to a
+    /// bucket in another region fail with `ObjectDoesNotExist` or `Unknown`. Paths created with
+    /// [S3Path::from_s3_client] are left as they are.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/bucket-in-another-region/bar.txt").follow_region_redirects();
+    ///   s3_path.exists();
+    ///
+    ///```
+    pub fn follow_region_redirects(mut self) -> Self {
+        self.service = self.service.follow_region_redirect();
+        self
+    }
+
+    /// Fail any request made through this path that takes longer than `timeout`,
+    /// returning [S3PathError::Timeout] instead of waiting on a hung connection.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   use std::time::Duration;
+    ///   let s3_path = S3Path::new("/foo/bar.txt").with_timeout(Duration::from_secs(30));
+    ///   s3_path.exists();
+    ///
+    ///```
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.service = self.service.with_timeout(timeout);
+        self
+    }
+
+    /// Transfer large objects through this path with the part size, concurrency and multipart
+    /// threshold of `config`. See [TransferConfig] for what it applies to.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::options::{TransferConfig, UploadOptions};
+    ///   use s3_fs::s3::S3Path;
+    ///   let config = TransferConfig::new().part_size(64 * 1024 * 1024);
+    ///   let s3_path = S3Path::new("/foo/big.bin").with_transfer_config(config);
+    ///   s3_path.upload_file("big.bin", &UploadOptions::default(), |_, _| {}).unwrap();
+    ///
+    ///```
+    pub fn with_transfer_config(mut self, config: TransferConfig) -> Self {
+        self.service.transfer = config;
+        self
+    }
+
+    /// Create, recognise and list directories through this path following the `marker`
+    /// convention, so the crate interoperates with tools such as Hadoop. Defaults to
+    /// [DirectoryMarker::TrailingSlash].
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::options::DirectoryMarker;
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/warehouse/events/")
+    ///       .with_directory_marker(DirectoryMarker::HadoopSuffix);
+    ///   s3_path.create_dir_all().unwrap();
+    ///   assert!(s3_path.is_dir());
+    ///
+    ///```
+    pub fn with_directory_marker(mut self, marker: DirectoryMarker) -> Self {
+        self.service.directory_marker = marker;
+        self
+    }
+
+    /// Write directory markers created through this path with `content_type`, or with none.
+    /// Defaults to [options::DIRECTORY_CONTENT_TYPE], which the AWS console shows as a folder.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::options::DirectoryMarker;
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/warehouse/events/")
+    ///       .with_directory_marker(DirectoryMarker::HadoopSuffix)
+    ///       .with_marker_content_type(None);
+    ///   s3_path.create_dir_all().unwrap();
+    ///
+    ///```
+    pub fn with_marker_content_type(mut self, content_type: Option<&str>) -> Self {
+        self.service.marker_content_type = content_type.map(str::to_string);
+        self
+    }
+
+    /// Creates the directory and each of its missing parents, writing the markers of the
+    /// configured [DirectoryMarker] convention. Like [crate::fs::create_dir_all], the created
+    /// directory is returned with the configuration of this path.
+    pub fn create_dir_all(&self) -> Result<S3Path, S3PathError> {
+        self.service.create_dir_all()
+    }
+
+    /// Lists the files and subdirectories directly under the path, treated as a directory.
+    /// Markers of the configured [DirectoryMarker] convention are listed as subdirectories.
+    pub fn read_dir(&self) -> Result<Listing, S3PathError> {
+        self.service
+            .with_key(sync::dir_prefix(&self.service.bucket.key))
+            .list_objects()
+    }
+
+    /// Uploads the local file `file` to the object, returning the number of bytes sent.
+    /// Like [crate::fs::upload_file], using the [TransferConfig] of the path.
+    pub fn upload_file<F>(
+        &self,
+        file: impl AsRef<Path>,
+        options: &UploadOptions,
+        mut progress: F,
+    ) -> Result<u64, S3PathError>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        options.validate()?;
+
+        let sent = self
+            .service
+            .upload_file(file.as_ref(), options, &mut progress)?;
+        self.invalidate();
+
+        Ok(sent)
+    }
+
+    /// Downloads the object to the local file `file` as `parts` concurrent ranged GETs.
+    /// Like [crate::fs::download_file_parallel], with at most the concurrency of the
+    /// [TransferConfig] of the path in flight.
+    pub fn download_file_parallel(
+        &self,
+        file: impl AsRef<Path>,
+        parts: u64,
+    ) -> Result<u64, S3PathError> {
+        self.service.download_file_parallel(file.as_ref(), parts)
     }
 
     /// Returns `true` if the object exists
@@ -97,17 +532,53 @@ impl S3Path {
     /// assert_eq!(s3_path.exists(), false)
     ///
     /// ```
-    /// # Panics
-    ///
-    /// Panics if the object does not exist
-    ///
+    pub fn exists(&self) -> bool {
+        self.cached_metadata().is_ok()
+    }
+
+    /// Returns `Ok(true)` if the object exists and `Ok(false)` if it does not.
     ///
-    #[allow(clippy::result_unit_err)]
+    /// Unlike [S3Path::exists], failures to find out are returned, such as
+    /// `S3PathError::WrongRegion` naming the bucket's region when the path targets another one.
+    /// # Examples
+    /// ```no_run
+    /// use s3_fs::errors::S3PathError;
+    /// use s3_fs::s3::S3Path;
+    /// match S3Path::new("/foo/bar").try_exists() {
+    ///     Ok(exists) => println!("exists: {}", exists),
+    ///     Err(S3PathError::WrongRegion(region)) => println!("the bucket is in {}", region),
+    ///     Err(e) => println!("{}", e),
+    /// }
+    /// ```
     pub fn try_exists(&self) -> Result<bool, S3PathError> {
-        self.service.ensure_object_exists()
+        match self.cached_metadata() {
+            Ok(_) => Ok(true),
+            Err(S3PathError::ObjectDoesNotExist) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the ETag of the object, or `None` if it does not exist, from a single HEAD request.
+    ///
+    /// The metadata cache is bypassed, so the ETag can be compared against a cached one to find
+    /// out whether the object changed.
+    /// # Examples
+    /// ```no_run
+    /// use s3_fs::s3::S3Path;
+    /// let cached_etag = Some("\"9b2cf535f27731c974343645a3985328\"".to_string());
+    /// let s3_path = S3Path::new("/foo/bar.csv");
+    /// if s3_path.head_etag().unwrap() != cached_etag {
+    ///     println!("{:?} changed", s3_path);
+    /// }
+    /// ```
+    pub fn head_etag(&self) -> Result<Option<String>, S3PathError> {
+        self.service.head_etag()
     }
 
-    /// Returns `true` if the `S3Path` is a directory
+    /// Returns `true` if the `S3Path` is a directory. An object at the key is one if the key ends
+    /// with `/`, whatever its content-type, or if its content-type is `application/x-directory`.
+    /// When no object exists at the key, the marker object of the configured [DirectoryMarker]
+    /// convention is looked up instead.
     /// # Examples
     /// ```
     ///
@@ -117,15 +588,34 @@ impl S3Path {
     ///
     ///```
     pub fn is_dir(&self) -> bool {
-        match self.exists() {
-            true => {
-                let metadata = self.service.get_object_metadata().unwrap();
-                metadata.content_type() == S3ObjectType::Directory
+        match self.cached_metadata() {
+            Ok(metadata) => {
+                self.service.bucket.key.ends_with('/')
+                    || metadata.content_type() == S3ObjectType::Directory
             }
-            false => false,
+            Err(S3PathError::ObjectDoesNotExist) => self.service.marker_exists().unwrap_or(false),
+            Err(_) => false,
         }
     }
 
+    /// Returns `true` if any object exists under the path taken as a directory, whether or not
+    /// a marker object exists for it. Unlike [S3Path::is_dir], which looks at the object at the
+    /// key, this finds folders that only exist as the prefix of their objects. A single listing
+    /// of one key is made.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/uploads");
+    ///   if s3_path.is_prefix().unwrap() && !s3_path.is_dir() {
+    ///       println!("a folder without a marker");
+    ///   }
+    ///
+    ///```
+    pub fn is_prefix(&self) -> Result<bool, S3PathError> {
+        self.service.has_objects_under()
+    }
+
     /// Returns `true` if the `S3Path` is a file
     /// # Examples
     /// ```
@@ -139,41 +629,485 @@ impl S3Path {
         !self.is_dir()
     }
 
-    /// Returns `true` if the `S3Path` is a file
+    /// Returns whether the `S3Path` is a file or a directory.
+    ///
+    /// A single HEAD request is made for the key. Keys ending with `/` and objects stored with
+    /// the `application/x-directory` content-type are directories. When no object exists at the
+    /// key, it is still a directory if any object lives under it, as S3 has no real directories.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::object::S3ObjectType;
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/bar");
+    ///   match s3_path.file_type().unwrap() {
+    ///       S3ObjectType::File => println!("a file"),
+    ///       S3ObjectType::Directory => println!("a directory"),
+    ///   }
+    ///
+    ///```
+    pub fn file_type(&self) -> Result<S3ObjectType, S3PathError> {
+        self.service.get_file_type()
+    }
+
+    /// Returns the metadata of the object. Its `Display` output is a concise, human-readable
+    /// line such as `1.2 MiB  text/csv  2024-01-02  file`.
     /// # Examples
     /// ```
     ///
     ///   use s3_fs::s3::S3Path;
     ///   let s3_path = S3Path::new("/foo/bar");
-    ///   s3_path.metadata();
+    ///   if let Ok(metadata) = s3_path.metadata() {
+    ///       println!("{}  {:?}", metadata, s3_path);
+    ///   }
     ///
     ///```
     pub fn metadata(&self) -> Result<ObjectMetadata, S3PathError> {
-        self.service.get_object_metadata()
+        self.cached_metadata()
     }
 
-    fn validate_path(path: &Path) {
-        if !path.starts_with("s3://") && path.is_relative() {
-            panic!("Found a relative path. S3Path only works with absolute paths.")
+    /// Returns the byte offsets of the object as the half-open range `0..len`, from its
+    /// metadata. The last byte is at `range.end - 1`, which is what the inclusive
+    /// `bytes=start-end` header of a ranged read expects.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/archive.zip");
+    ///   let range = s3_path.byte_range().unwrap();
+    ///   let start = range.end.saturating_sub(22);
+    ///   let trailer = s3_path.read_range_into(start, &mut [0; 22]).unwrap();
+    ///
+    ///```
+    pub fn byte_range(&self) -> Result<Range<u64>, S3PathError> {
+        let len = self.cached_metadata()?.content_length.unwrap_or_default();
+
+        Ok(0..len.max(0) as u64)
+    }
+
+    /// Returns `true` if both objects have the same contents, which may live in different buckets.
+    ///
+    /// Objects of different sizes differ. Equal ETags, or two ETags that are both MD5s, settle the
+    /// comparison from the two HEAD requests alone. Otherwise, e.g. when one object was uploaded in
+    /// parts or is encrypted with SSE-KMS, both are read and compared 8 MiB at a time, so neither
+    /// is held in memory.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let original = S3Path::new("/old-bucket/data.parquet");
+    ///   let migrated = S3Path::new("/new-bucket/data.parquet");
+    ///   assert!(original.content_equals(&migrated).unwrap());
+    ///
+    ///```
+    pub fn content_equals(&self, other: &S3Path) -> Result<bool, S3PathError> {
+        let metadata = self.cached_metadata()?;
+        let other_metadata = other.cached_metadata()?;
+
+        if metadata.content_length != other_metadata.content_length {
+            return Ok(false);
+        }
+        if let (Some(md5), Some(other_md5)) = (metadata.md5(), other_metadata.md5()) {
+            return Ok(md5 == other_md5);
+        }
+        if metadata.e_tag == other_metadata.e_tag {
+            return Ok(true);
+        }
+
+        let length = metadata.content_length.unwrap_or_default().max(0) as u64;
+        let mut start = 0;
+        while start < length {
+            let end = (start + COMPARE_CHUNK_SIZE).min(length) - 1;
+            if self.service.read_range(start, end)? != other.service.read_range(start, end)? {
+                return Ok(false);
+            }
+            start = end + 1;
+        }
+
+        Ok(true)
+    }
+
+    /// Cache the metadata of the object after the first lookup, so that [S3Path::exists],
+    /// [S3Path::is_dir], [S3Path::is_file] and [S3Path::metadata] share a single HEAD request.
+    ///
+    /// Failed lookups are not cached. Call [S3Path::invalidate] once the object may have changed.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/bar.txt").with_metadata_cache();
+    ///   if s3_path.exists() {
+    ///       println!("{}", s3_path.metadata().unwrap());
+    ///   }
+    ///
+    ///```
+    pub fn with_metadata_cache(mut self) -> Self {
+        self.metadata_cache = Some(Mutex::new(None));
+        self
+    }
+
+    /// Discard the cached metadata, so that the next lookup fetches it again.
+    pub fn invalidate(&self) {
+        if let Some(cache) = &self.metadata_cache {
+            *cache.lock().unwrap() = None;
         }
     }
 
+    fn cached_metadata(&self) -> Result<ObjectMetadata, S3PathError> {
+        let cache = match &self.metadata_cache {
+            Some(cache) => cache,
+            None => return self.service.get_object_metadata(),
+        };
+
+        let mut cache = cache.lock().unwrap();
+        match cache.as_ref() {
+            Some(metadata) => Ok(metadata.clone()),
+            None => {
+                let metadata = self.service.get_object_metadata()?;
+                *cache = Some(metadata.clone());
+                Ok(metadata)
+            }
+        }
+    }
+
+    /// Reads up to `buf.len()` bytes of the object starting at byte `start` into `buf`, returning
+    /// the number of bytes read. Fewer bytes are read only when the object ends first, and none
+    /// when `start` is past its end. No buffer is allocated, so `buf` can be reused across calls.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/table.parquet");
+    ///   let mut page = vec![0; 64 * 1024];
+    ///   let read = s3_path.read_range_into(128 * 1024, &mut page).unwrap();
+    ///   println!("{:?}", &page[..read]);
+    ///
+    ///```
+    pub fn read_range_into(&self, start: u64, buf: &mut [u8]) -> Result<usize, S3PathError> {
+        self.service.read_range_into(start, buf)
+    }
+
+    /// Runs the SQL `expression` over the object server-side with S3 Select, returning only the
+    /// matching records, serialized as `output`. The object is referred to as `s3object`.
+    ///
+    /// The records arrive framed as an event stream, which is decoded and checked here. A query
+    /// S3 rejects while running returns `S3PathError::SelectFailed` with S3's error message.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::options::{SelectInput, SelectOutput};
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/requests.csv");
+    ///   let errors = s3_path
+    ///       .select(
+    ///           "SELECT * FROM s3object s WHERE s.status = 'error'",
+    ///           &SelectInput::Csv { has_header: true },
+    ///           &SelectOutput::Csv,
+    ///       )
+    ///       .unwrap();
+    ///   println!("{}", String::from_utf8_lossy(&errors));
+    ///
+    ///```
+    pub fn select(
+        &self,
+        expression: &str,
+        input: &SelectInput,
+        output: &SelectOutput,
+    ) -> Result<Vec<u8>, S3PathError> {
+        self.service.select(expression, input, output)
+    }
+
+    /// Returns the additional checksum S3 stored for the object with `algorithm`, base64 encoded,
+    /// or `None` if the object was uploaded without one.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::checksum::ChecksumAlgorithm;
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/bar.txt");
+    ///   let stored = s3_path.checksum(ChecksumAlgorithm::Crc32c).unwrap();
+    ///   assert_eq!(stored, Some(ChecksumAlgorithm::Crc32c.compute(b"hello")));
+    ///
+    ///```
+    pub fn checksum(&self, algorithm: ChecksumAlgorithm) -> Result<Option<String>, S3PathError> {
+        self.service.get_checksum(algorithm)
+    }
+
+    /// Writes `contents` to the object along with user `metadata`, sent as `x-amz-meta-*` headers.
+    ///
+    /// Returns `S3PathError::InvalidMetadata` without writing anything if a key is not a valid
+    /// header name or a value is not printable ASCII. S3 stores the keys lowercased.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   use std::collections::HashMap;
+    ///   let s3_path = S3Path::new("/foo/bar.txt");
+    ///   let metadata = HashMap::from([("author".to_string(), "me".to_string())]);
+    ///   s3_path.write_with_metadata("hello", metadata).unwrap();
+    ///
+    ///   let stored = s3_path.metadata().unwrap().metadata.unwrap();
+    ///   assert_eq!(stored["author"], "me");
+    ///
+    ///```
+    pub fn write_with_metadata<C: AsRef<[u8]>>(
+        &self,
+        contents: C,
+        metadata: HashMap<String, String>,
+    ) -> Result<(), S3PathError> {
+        options::validate_metadata(&metadata)?;
+        let contents = contents.as_ref().to_vec();
+
+        self.service.write_to_object(
+            Some(contents.len() as i64),
+            Some(contents.into()),
+            Some(metadata),
+            &UploadOptions::default(),
+        )?;
+        self.invalidate();
+
+        Ok(())
+    }
+
+    /// Replaces the user metadata of the object in place, with a server-side copy of the object
+    /// onto itself, so its bytes are neither downloaded nor uploaded again.
+    ///
+    /// The content-type, `Cache-Control`, `Content-Disposition`, `Content-Encoding`, `Expires`
+    /// and storage class of the object are kept, as are its tags. Its ACL is reset to the bucket
+    /// default. Returns `S3PathError::PreconditionFailed` if the object changed while it was being
+    /// updated. See [crate::fs::copy_with_metadata] to also change the content-type.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   use std::collections::HashMap;
+    ///   let s3_path = S3Path::new("/foo/report.csv");
+    ///   let metadata = HashMap::from([("reviewed".to_string(), "yes".to_string())]);
+    ///   s3_path.set_metadata(metadata).unwrap();
+    ///
+    ///```
+    pub fn set_metadata(&self, metadata: HashMap<String, String>) -> Result<(), S3PathError> {
+        options::validate_metadata(&metadata)?;
+
+        self.service.set_metadata(metadata)?;
+        self.invalidate();
+
+        Ok(())
+    }
+
+    /// Requests a temporary readable copy of an object archived in `GLACIER` or `DEEP_ARCHIVE`,
+    /// kept for `days` days. Requesting a restore that is already in progress succeeds.
+    ///
+    /// Reading an archived object fails with `S3PathError::ObjectArchived` until the restore
+    /// completes, which [ObjectMetadata::restore_status] reports.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::object::RestoreStatus;
+    ///   use s3_fs::options::Tier;
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/archive.tar");
+    ///   s3_path.restore(7, Tier::Standard).unwrap();
+    ///
+    ///   while s3_path.metadata().unwrap().restore_status() == RestoreStatus::InProgress {
+    ///       std::thread::sleep(std::time::Duration::from_secs(600));
+    ///   }
+    ///
+    ///```
+    pub fn restore(&self, days: i32, tier: Tier) -> Result<(), S3PathError> {
+        self.service.restore_object(days, tier)?;
+        self.invalidate();
+
+        Ok(())
+    }
+
+    /// Opens the object for reading through `std::io::Read` and `std::io::Seek`.
+    /// See [S3Reader] for how the object is fetched.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   use std::io::{Read, Seek, SeekFrom};
+    ///   let mut reader = S3Path::new("/foo/data.parquet").reader().unwrap();
+    ///
+    ///   let mut footer = [0; 8];
+    ///   reader.seek(SeekFrom::End(-8)).unwrap();
+    ///   reader.read_exact(&mut footer).unwrap();
+    ///
+    ///```
+    pub fn reader(&self) -> Result<S3Reader, S3PathError> {
+        S3Reader::new(self.service.clone())
+    }
+
+    /// Streams the object into `writer` with a single GET, returning the number of bytes copied.
+    /// Each chunk is written as it arrives, so the object is never held in memory whole.
+    ///
+    /// A failure to write is returned as `S3PathError::LocalFile`.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use md5::{Digest, Md5};
+    ///   use s3_fs::s3::S3Path;
+    ///   use std::io::{self, Write};
+    ///
+    ///   struct Hasher(Md5);
+    ///   impl Write for Hasher {
+    ///       fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    ///           self.0.update(buf);
+    ///           Ok(buf.len())
+    ///       }
+    ///       fn flush(&mut self) -> io::Result<()> {
+    ///           Ok(())
+    ///       }
+    ///   }
+    ///
+    ///   let mut hasher = Hasher(Md5::new());
+    ///   let copied = S3Path::new("/foo/data.csv").copy_to_writer(&mut hasher).unwrap();
+    ///   println!("{} bytes, md5 {:x}", copied, hasher.0.finalize());
+    ///
+    ///```
+    pub fn copy_to_writer<W: Write>(&self, writer: &mut W) -> Result<u64, S3PathError> {
+        self.service.copy_to_writer(writer)
+    }
+
+    /// Creates the bucket this path lives in, in `region`.
+    ///
+    /// Returns `S3PathError::BucketAlreadyOwned` if you already own the bucket, and
+    /// `S3PathError::BucketNameTaken` if another account does.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use rusoto_core::Region;
+    ///   use s3_fs::fs;
+    ///   use s3_fs::s3::S3Path;
+    ///   S3Path::new("/fresh-bucket/").create_bucket(Region::EuWest1).unwrap();
+    ///   fs::write("/fresh-bucket/hello.txt", "hello").unwrap();
+    ///
+    ///```
+    pub fn create_bucket(&self, region: Region) -> Result<(), S3PathError> {
+        self.service.create_bucket(region)
+    }
+
+    /// Deletes the bucket this path lives in.
+    ///
+    /// Returns `S3PathError::BucketNotEmpty` if the bucket still holds objects.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   S3Path::new("/old-bucket/").delete_bucket().unwrap();
+    ///
+    ///```
+    pub fn delete_bucket(&self) -> Result<(), S3PathError> {
+        self.service.delete_bucket()
+    }
+
+    /// Returns a URL anyone can use to read the object until `expires_in` has passed, up to
+    /// 7 days. The headers of the response are replaced by those set in `overrides`.
+    ///
+    /// # Note
+    /// The URL is signed with the credentials and region of the path, e.g. those of the role for
+    /// [S3Path::with_assumed_role]. Paths created with [S3Path::from_s3_client] have no known
+    /// credentials and fail with `S3PathError::UnknownClient`.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::options::ResponseOverrides;
+    ///   use s3_fs::s3::S3Path;
+    ///   use std::time::Duration;
+    ///   let overrides = ResponseOverrides::new().content_disposition("attachment");
+    ///   let url = S3Path::new("/foo/report.csv")
+    ///       .presigned_get(Duration::from_secs(3600), &overrides)
+    ///       .unwrap();
+    ///
+    ///```
+    pub fn presigned_get(
+        &self,
+        expires_in: Duration,
+        overrides: &ResponseOverrides,
+    ) -> Result<String, S3PathError> {
+        self.service.presigned_get(expires_in, overrides)
+    }
+
+    /// Returns the access control list of the object.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   let s3_path = S3Path::new("/foo/bar.txt");
+    ///   if s3_path.acl().unwrap().is_public() {
+    ///       println!("{:?} is public", s3_path);
+    ///   }
+    ///
+    ///```
+    pub fn acl(&self) -> Result<Acl, S3PathError> {
+        self.service.get_acl()
+    }
+
+    /// Returns the raw body of the object as a stream of bytes, without buffering it.
+    ///
+    /// Unlike the blocking methods, this must be awaited from within your own tokio runtime,
+    /// which keeps the underlying connection alive while the body is consumed.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use s3_fs::s3::S3Path;
+    ///   use tokio::io::AsyncReadExt;
+    ///
+    ///   # async fn run() {
+    ///   let s3_path = S3Path::new("/foo/bar.txt");
+    ///   let mut contents = vec![];
+    ///   let body = s3_path.body_stream().await.unwrap();
+    ///   body.into_async_read().read_to_end(&mut contents).await.unwrap();
+    ///   # }
+    ///```
+    pub async fn body_stream(&self) -> Result<StreamingBody, S3PathError> {
+        self.service.get_object_stream().await
+    }
+
+    /// Returns the body of the object as a stream of `Bytes` chunks, for async consumers such as
+    /// an HTTP response body. Like [S3Path::body_stream], it must be awaited from within your own
+    /// tokio runtime. A chunk that fails to arrive is yielded as an error.
+    /// # Examples
+    /// ```no_run
+    ///
+    ///   use futures::StreamExt;
+    ///   use s3_fs::s3::S3Path;
+    ///
+    ///   # async fn run() {
+    ///   let s3_path = S3Path::new("/foo/video.mp4");
+    ///   let mut stream = s3_path.read_stream().await.unwrap();
+    ///   while let Some(chunk) = stream.next().await {
+    ///       println!("{} bytes", chunk.unwrap().len());
+    ///   }
+    ///   # }
+    ///```
+    pub async fn read_stream(
+        &self,
+    ) -> Result<impl Stream<Item = Result<Bytes, S3PathError>>, S3PathError> {
+        let body = self.service.get_object_stream().await?;
+
+        Ok(body.map(|chunk| chunk.map_err(|_| S3PathError::Unknown)))
+    }
+
+    pub(crate) fn from_service(service: S3Service) -> Self {
+        let path = PathBuf::from(format!("/{}/{}", service.bucket.name, service.bucket.key));
+
+        S3Path {
+            path,
+            service,
+            metadata_cache: None,
+        }
+    }
+
+    /// Normalizes `path` to `/bucket/key` following the rules of [BucketConfig::from_path].
     fn clean_path<P: ToString + Copy>(path: P) -> PathBuf {
         let path = path.to_string();
-        if path.starts_with("s3://") {
-            PathBuf::from(
-                path.splitn(4, '/')
-                    .collect::<Vec<&str>>()
-                    .last()
-                    .unwrap()
-                    .to_string(),
-            )
-        } else {
-            let path = PathBuf::from(path);
-            if path.is_relative() {
-                panic!("Found a relative path. S3Path only works with absolute paths.")
-            }
-            path
+        if !path.starts_with("s3://") && Path::new(&path).is_relative() {
+            panic!("Found a relative path. S3Path only works with absolute paths.")
         }
+
+        let bucket = BucketConfig::from_path(path);
+        PathBuf::from(format!("/{}/{}", bucket.name, bucket.key))
     }
 }
@@ -0,0 +1,152 @@
+use crate::errors::S3PathError;
+use crate::options::{SelectInput, SelectOutput};
+
+/// The total length, headers length and prelude CRC of each message.
+const PRELUDE_LENGTH: usize = 12;
+/// The CRC of the whole message, after its payload.
+const MESSAGE_CRC_LENGTH: usize = 4;
+
+const HEADER_STRING: u8 = 7;
+
+/// Renders the body of a `SelectObjectContent` request.
+pub(crate) fn request_body(
+    expression: &str,
+    input: &SelectInput,
+    output: &SelectOutput,
+) -> Vec<u8> {
+    format!(
+        "<SelectObjectContentRequest xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+         <Expression>{}</Expression><ExpressionType>SQL</ExpressionType>\
+         <InputSerialization>{}</InputSerialization>\
+         <OutputSerialization>{}</OutputSerialization>\
+         </SelectObjectContentRequest>",
+        escape(expression),
+        input.as_xml(),
+        output.as_xml()
+    )
+    .into_bytes()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Decodes the event stream of a `SelectObjectContent` response, returning the payloads of its
+/// `Records` events joined together.
+///
+/// Each message is framed by its length and checked against its CRCs. An `error` message is
+/// returned as `S3PathError::SelectFailed`, and a stream that stops before its `End` event, which
+/// S3 sends once every record was delivered, is an error as well.
+pub(crate) fn records(mut stream: &[u8]) -> Result<Vec<u8>, S3PathError> {
+    let mut records = vec![];
+
+    while !stream.is_empty() {
+        let message = Message::decode(&mut stream)?;
+
+        match (
+            message.header(":message-type"),
+            message.header(":event-type"),
+        ) {
+            (Some("event"), Some("Records")) => records.extend_from_slice(message.payload),
+            (Some("event"), Some("End")) => return Ok(records),
+            (Some("event"), _) => {}
+            (Some("error"), _) => {
+                return Err(S3PathError::SelectFailed(format!(
+                    "{}: {}",
+                    message.header(":error-code").unwrap_or_default(),
+                    message.header(":error-message").unwrap_or_default()
+                )))
+            }
+            _ => return Err(S3PathError::Unknown),
+        }
+    }
+
+    Err(S3PathError::Unknown)
+}
+
+struct Message<'a> {
+    headers: Vec<(&'a str, &'a str)>,
+    payload: &'a [u8],
+}
+
+impl<'a> Message<'a> {
+    /// Takes the next message off the front of `stream`.
+    fn decode(stream: &mut &'a [u8]) -> Result<Self, S3PathError> {
+        if stream.len() < PRELUDE_LENGTH {
+            return Err(S3PathError::Unknown);
+        }
+
+        let total_length = u32_be(&stream[0..4]) as usize;
+        let headers_length = u32_be(&stream[4..8]) as usize;
+        if total_length < PRELUDE_LENGTH + headers_length + MESSAGE_CRC_LENGTH
+            || stream.len() < total_length
+        {
+            return Err(S3PathError::Unknown);
+        }
+
+        let (message, rest) = stream.split_at(total_length);
+        let crc_start = total_length - MESSAGE_CRC_LENGTH;
+        if crc32fast::hash(&message[0..8]) != u32_be(&message[8..12])
+            || crc32fast::hash(&message[..crc_start]) != u32_be(&message[crc_start..])
+        {
+            return Err(S3PathError::Unknown);
+        }
+        *stream = rest;
+
+        let payload_start = PRELUDE_LENGTH + headers_length;
+        Ok(Message {
+            headers: decode_headers(&message[PRELUDE_LENGTH..payload_start])?,
+            payload: &message[payload_start..crc_start],
+        })
+    }
+
+    fn header(&self, name: &str) -> Option<&'a str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| *header == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Decodes the string headers of a message. S3 only sends string headers with Select events.
+fn decode_headers(mut headers: &[u8]) -> Result<Vec<(&str, &str)>, S3PathError> {
+    let mut decoded = vec![];
+
+    while !headers.is_empty() {
+        let name_length = headers[0] as usize;
+        let name = take(&mut headers, 1, name_length)?;
+        if take(&mut headers, 0, 1)? != [HEADER_STRING] {
+            return Err(S3PathError::Unknown);
+        }
+        let value_length = u16::from_be_bytes(take(&mut headers, 0, 2)?.try_into().unwrap());
+        let value = take(&mut headers, 0, value_length as usize)?;
+
+        decoded.push((utf8(name)?, utf8(value)?));
+    }
+
+    Ok(decoded)
+}
+
+/// Skips `skip` bytes of `bytes`, then takes the next `length` bytes off its front.
+fn take<'a>(bytes: &mut &'a [u8], skip: usize, length: usize) -> Result<&'a [u8], S3PathError> {
+    let end = skip + length;
+    if bytes.len() < end {
+        return Err(S3PathError::Unknown);
+    }
+
+    let taken = &bytes[skip..end];
+    *bytes = &bytes[end..];
+    Ok(taken)
+}
+
+fn utf8(bytes: &[u8]) -> Result<&str, S3PathError> {
+    std::str::from_utf8(bytes).map_err(|_| S3PathError::Unknown)
+}
+
+fn u32_be(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes.try_into().unwrap())
+}
@@ -1,67 +1,406 @@
+use crate::acl::{Acl, Grant};
 use crate::bucket::BucketConfig;
-use crate::errors::{process_error, S3PathError, S3PathOp};
-use crate::object::ObjectMetadata;
-use rusoto_core::{Region, RusotoError};
+use crate::checksum::ChecksumAlgorithm;
+use crate::credentials::AssumeRoleProvider;
+use crate::errors::{
+    process_delete_error, process_error, process_get_object_error, S3PathError, S3PathOp,
+};
+use crate::etag::{ETag, MultipartETag};
+use crate::gzip;
+use crate::listing::{Listing, ObjectOwner, ObjectVersion};
+use crate::object::{ObjectMetadata, S3ObjectType};
+use crate::options::{
+    DirectoryMarker, MetadataDirective, ObjectLock, ResponseOverrides, SelectInput, SelectOutput,
+    TaggingDirective, Tier, TransferConfig, UploadOptions, DIRECTORY_CONTENT_TYPE,
+};
+use crate::s3::S3Path;
+use crate::select;
+use crate::sync::{self, BatchResult};
+use chrono::{DateTime, FixedOffset};
+use futures::stream::{self, FuturesUnordered, StreamExt};
+use md5::{Digest, Md5};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rusoto_core::credential::{
+    AutoRefreshingProvider, AwsCredentials, DefaultCredentialsProvider, ProvideAwsCredentials,
+    StaticProvider,
+};
+use rusoto_core::request::{BufferedHttpResponse, HttpDispatchError};
+use rusoto_core::signature::SignedRequest;
+use rusoto_core::{Client, HttpClient, Region, RusotoError};
+use rusoto_s3::util::{PreSignedRequest, PreSignedRequestOption};
 use rusoto_s3::{
-    GetObjectError, GetObjectOutput, GetObjectRequest, HeadObjectError, HeadObjectOutput,
-    HeadObjectRequest, PutObjectError, PutObjectOutput, PutObjectRequest, S3Client, StreamingBody,
-    S3,
+    AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload,
+    CompletedPart, CopyObjectOutput, CopyObjectRequest, CreateBucketConfiguration,
+    CreateBucketError, CreateBucketRequest, CreateMultipartUploadRequest, Delete,
+    DeleteBucketRequest, DeleteObjectRequest, DeleteObjectsError, DeleteObjectsOutput,
+    DeleteObjectsRequest, GetObjectAclError, GetObjectAclOutput, GetObjectAclRequest,
+    GetObjectError, GetObjectOutput, GetObjectRequest, GetObjectTaggingRequest,
+    GlacierJobParameters, HeadObjectError, HeadObjectOutput, HeadObjectRequest,
+    ListMultipartUploadsRequest, ListObjectVersionsRequest, ListObjectsV2Error,
+    ListObjectsV2Output, ListObjectsV2Request, ListPartsRequest, MultipartUpload, Object,
+    ObjectIdentifier, PutObjectError, PutObjectOutput, PutObjectRequest, RestoreObjectRequest,
+    RestoreRequest, S3Client, StreamingBody, UploadPartCopyRequest, UploadPartRequest, S3,
 };
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::io::{self, Read, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, ReadBuf};
+
+pub(crate) const MAX_CONCURRENT_REQUESTS: usize = 16;
+
+const MAX_KEYS_PER_DELETE: usize = 1000;
+
+/// The largest object `CopyObject` accepts; anything larger is copied in parts.
+pub(crate) const MAX_COPY_OBJECT_SIZE: i64 = 5 * 1024 * 1024 * 1024;
+
+/// The most parts a multipart upload can have.
+const MAX_PARTS: u64 = 10_000;
+
+/// How many times a listing page that failed with a transient error is requested again.
+const LIST_PAGE_RETRIES: u32 = 5;
 
+/// The wait before the first retry of a listing page, doubled before each of the next ones.
+const LIST_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// The message rusoto's HTTP client reports when a request exceeds its timeout.
+pub(crate) const TIMEOUT_MESSAGE: &str = "Timeout while dispatching request";
+
+const COPY_SOURCE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+#[derive(Clone)]
 pub struct S3Service {
     pub bucket: BucketConfig,
     pub client: S3Client,
+    pub requester_pays: bool,
+    pub expected_owner: Option<String>,
+    pub timeout: Option<Duration>,
+    pub max_keys: Option<i64>,
+    pub recursive: bool,
+    pub fetch_owner: bool,
+    pub transfer: TransferConfig,
+    pub directory_marker: DirectoryMarker,
+    /// The content-type written on directory markers, if any.
+    pub marker_content_type: Option<String>,
+    /// The client and region `client` was built from, if known. Requests signed by hand need them.
+    core: Option<Core>,
+}
+
+/// What requests signed by hand need: the client and region the `S3Client` was built from, and
+/// the credentials that client signs with.
+#[derive(Clone)]
+struct Core {
+    client: Client,
+    region: Region,
+    credentials: Arc<dyn ProvideAwsCredentials + Send + Sync>,
 }
 
 impl Debug for S3Service {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("S3Service")
             .field("bucket", &self.bucket)
+            .field("requester_pays", &self.requester_pays)
+            .field("expected_owner", &self.expected_owner)
+            .field("timeout", &self.timeout)
+            .field("max_keys", &self.max_keys)
+            .field("recursive", &self.recursive)
+            .field("fetch_owner", &self.fetch_owner)
+            .field("transfer", &self.transfer)
+            .field("directory_marker", &self.directory_marker)
+            .field("marker_content_type", &self.marker_content_type)
             .finish()
     }
 }
 
 impl S3Service {
     pub fn new(path: String) -> Self {
-        let client = S3Client::new(Region::default());
-        let bucket = BucketConfig::from_path(path);
-        S3Service { bucket, client }
+        Self::in_region(path, Region::default())
     }
 
     pub fn from_client(path: String, client: S3Client) -> Self {
-        let bucket = BucketConfig::from_path(path);
-        S3Service { bucket, client }
+        Self::from_bucket_client(BucketConfig::from_path(path), client)
+    }
+
+    /// Targets `bucket` as given, without parsing a path.
+    pub fn from_bucket(bucket: BucketConfig) -> Self {
+        let client = S3Client::new_with_client(Client::shared(), Region::default());
+        S3Service {
+            core: Some(Core {
+                client: Client::shared(),
+                region: Region::default(),
+                credentials: Arc::new(DefaultCredentialsProvider::new().unwrap()),
+            }),
+            ..Self::from_bucket_client(bucket, client)
+        }
+    }
+
+    fn from_bucket_client(bucket: BucketConfig, client: S3Client) -> Self {
+        S3Service {
+            bucket,
+            client,
+            requester_pays: false,
+            expected_owner: None,
+            timeout: None,
+            max_keys: None,
+            recursive: false,
+            fetch_owner: false,
+            transfer: TransferConfig::default(),
+            directory_marker: DirectoryMarker::default(),
+            marker_content_type: Some(DIRECTORY_CONTENT_TYPE.to_string()),
+            core: None,
+        }
+    }
+
+    pub fn in_region(path: String, region: Region) -> Self {
+        // The shared client signs with the default credentials too.
+        let credentials = Arc::new(DefaultCredentialsProvider::new().unwrap());
+        Self::from_core(path, Client::shared(), credentials, region)
+    }
+
+    /// Signs every request, including those this crate signs by hand and presigned URLs, with
+    /// `credentials`, and sends them to `region`.
+    pub fn from_credentials<C>(path: String, credentials: C, region: Region) -> Self
+    where
+        C: ProvideAwsCredentials + Send + Sync + 'static,
+    {
+        let credentials = Arc::new(credentials);
+        let core_client = Client::new_with(credentials.clone(), HttpClient::new().unwrap());
+        Self::from_core(path, core_client, credentials, region)
+    }
+
+    fn from_core(
+        path: String,
+        client: Client,
+        credentials: Arc<dyn ProvideAwsCredentials + Send + Sync>,
+        region: Region,
+    ) -> Self {
+        S3Service {
+            core: Some(Core {
+                client: client.clone(),
+                region: region.clone(),
+                credentials,
+            }),
+            ..Self::from_client(path, S3Client::new_with_client(client, region))
+        }
+    }
+
+    /// The client, region and credentials to sign requests with, or
+    /// [S3PathError::UnknownClient] for a service built from an `S3Client` as is.
+    fn core(&self) -> Result<&Core, S3PathError> {
+        self.core.as_ref().ok_or(S3PathError::UnknownClient)
+    }
+
+    /// Whether requests signed by hand, such as conditional writes, can be sent.
+    pub(crate) fn signs_raw_requests(&self) -> bool {
+        self.core.is_some()
+    }
+
+    pub fn from_assumed_role(path: String, role_arn: String, session_name: String) -> Self {
+        let provider = AutoRefreshingProvider::new(AssumeRoleProvider::new(
+            role_arn,
+            session_name,
+            Region::default(),
+        ))
+        .unwrap();
+        Self::from_credentials(path, provider, Region::default())
+    }
+
+    /// Sends unsigned requests, which S3 accepts for public buckets and objects.
+    pub fn anonymous(path: String) -> Self {
+        // Empty credentials are anonymous, and rusoto leaves anonymous requests unsigned.
+        let provider = StaticProvider::from(AwsCredentials::default());
+        Self::from_credentials(path, provider, Region::default())
+    }
+
+    pub fn with_key<P: ToString>(&self, key: P) -> Self {
+        let mut service = self.clone();
+        service.bucket.key = key.to_string();
+        service
+    }
+
+    /// The client requests are made with, for operations this crate does not wrap.
+    pub fn client(&self) -> &S3Client {
+        &self.client
+    }
+
+    /// Fail any request that takes longer than `timeout` with [S3PathError::Timeout].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retargets the client to the region the bucket lives in, if S3 reports a different one.
+    ///
+    /// S3 answers requests sent to the wrong region with a 301 redirect carrying the bucket's
+    /// region in the `x-amz-bucket-region` header. A HEAD on the bucket triggers it once, and the
+    /// client is rebuilt for that region. On any failure the current region is kept, as it is
+    /// for a service built from an `S3Client` as is, whose credentials and region are unknown.
+    pub fn follow_region_redirect(mut self) -> Self {
+        if let (Some(region), Some(core)) = (self.bucket_region(), &mut self.core) {
+            if region != core.region {
+                self.client = S3Client::new_with_client(core.client.clone(), region.clone());
+                core.region = region;
+            }
+        }
+        self
+    }
+
+    #[tokio::main]
+    async fn bucket_region(&self) -> Option<Region> {
+        let core = self.core().ok()?;
+        let path = format!("/{}", self.bucket.name);
+        let request = SignedRequest::new("HEAD", "s3", &core.region, &path);
+
+        let response = self
+            .timed("HeadBucket", async {
+                Ok::<_, RusotoError<()>>(core.client.sign_and_dispatch(request).await?)
+            })
+            .await
+            .ok()?;
+        let region = response.headers.get("x-amz-bucket-region")?;
+
+        Region::from_str(region).ok()
+    }
+
+    /// Sends `request`, giving up after the configured timeout. `operation` names the S3 API
+    /// call in the span recorded when the `tracing` feature is enabled.
+    #[allow(clippy::result_large_err)]
+    async fn timed<T, E: Debug>(
+        &self,
+        operation: &str,
+        request: impl Future<Output = Result<T, RusotoError<E>>>,
+    ) -> Result<T, RusotoError<E>> {
+        let request = async {
+            match self.timeout {
+                Some(timeout) => {
+                    tokio::time::timeout(timeout, request)
+                        .await
+                        .unwrap_or_else(|_| {
+                            Err(RusotoError::HttpDispatch(HttpDispatchError::new(
+                                TIMEOUT_MESSAGE.to_string(),
+                            )))
+                        })
+                }
+                None => request.await,
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        let request = crate::trace::instrument(&self.bucket, operation, request);
+        #[cfg(not(feature = "tracing"))]
+        let _ = operation;
+
+        request.await
+    }
+
+    fn request_payer(&self) -> Option<String> {
+        match self.requester_pays {
+            true => Some("requester".to_string()),
+            false => None,
+        }
+    }
+
+    fn raw_request(&self, method: &str) -> Result<SignedRequest, S3PathError> {
+        let core = self.core()?;
+        let path = format!("/{}/{}", self.bucket.name, self.bucket.key);
+        let mut request = SignedRequest::new(method, "s3", &core.region, &path);
+        request.add_optional_header("x-amz-request-payer", self.request_payer());
+        request.add_optional_header("x-amz-expected-bucket-owner", self.expected_owner.as_ref());
+        Ok(request)
+    }
+
+    #[tokio::main]
+    async fn dispatch(
+        &self,
+        request: SignedRequest,
+        op: S3PathOp,
+    ) -> Result<BufferedHttpResponse, S3PathError> {
+        self.send(request, op).await
+    }
+
+    async fn send(
+        &self,
+        request: SignedRequest,
+        op: S3PathOp,
+    ) -> Result<BufferedHttpResponse, S3PathError> {
+        let core = self.core()?;
+        let response = self
+            .timed(&format!("{:?}", op), async {
+                let mut response = core
+                    .client
+                    .sign_and_dispatch(request)
+                    .await
+                    .map_err(RusotoError::<()>::from)?;
+                Ok(response.buffer().await?)
+            })
+            .await
+            .map_err(|e| process_error(Some(e), None, op))?;
+
+        match response.status.is_success() {
+            true => Ok(response),
+            false => Err(process_error::<()>(
+                Some(RusotoError::Unknown(response)),
+                None,
+                op,
+            )),
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn object_exists(&self) -> Result<HeadObjectOutput, RusotoError<HeadObjectError>> {
+        self.head_object(self.bucket.key.to_string())
     }
 
+    #[allow(clippy::result_large_err)]
     #[tokio::main]
-    pub async fn object_exists(&self) -> Result<HeadObjectOutput, RusotoError<HeadObjectError>> {
+    async fn head_object<P: ToString>(
+        &self,
+        path: P,
+    ) -> Result<HeadObjectOutput, RusotoError<HeadObjectError>> {
+        self.head(path).await
+    }
+
+    async fn head<P: ToString>(
+        &self,
+        path: P,
+    ) -> Result<HeadObjectOutput, RusotoError<HeadObjectError>> {
         let head_object_input = HeadObjectRequest {
             bucket: self.bucket.name.to_string(),
-            expected_bucket_owner: None,
+            expected_bucket_owner: self.expected_owner.clone(),
             if_match: None,
             if_modified_since: None,
             if_none_match: None,
             if_unmodified_since: None,
-            key: self.bucket.key.to_string(),
+            key: path.to_string(),
             part_number: None,
             range: None,
-            request_payer: None,
+            request_payer: self.request_payer(),
             sse_customer_algorithm: None,
             sse_customer_key: None,
             sse_customer_key_md5: None,
             version_id: None,
         };
 
-        self.client.head_object(head_object_input).await
+        self.timed("HeadObject", self.client.head_object(head_object_input))
+            .await
     }
 
-    #[tokio::main]
-    async fn get_object(&self) -> Result<GetObjectOutput, RusotoError<GetObjectError>> {
-        let get_object_input = GetObjectRequest {
+    fn get_object_request(&self) -> GetObjectRequest {
+        GetObjectRequest {
             bucket: self.bucket.name.to_string(),
-            expected_bucket_owner: None,
+            expected_bucket_owner: self.expected_owner.clone(),
             if_match: None,
             if_modified_since: None,
             if_none_match: None,
@@ -69,7 +408,7 @@ impl S3Service {
             key: self.bucket.key.to_string(),
             part_number: None,
             range: None,
-            request_payer: None,
+            request_payer: self.request_payer(),
             response_cache_control: None,
             response_content_disposition: None,
             response_content_encoding: None,
@@ -80,43 +419,285 @@ impl S3Service {
             sse_customer_key: None,
             sse_customer_key_md5: None,
             version_id: None,
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    #[tokio::main]
+    async fn get_object(&self) -> Result<GetObjectOutput, RusotoError<GetObjectError>> {
+        self.timed(
+            "GetObject",
+            self.client.get_object(self.get_object_request()),
+        )
+        .await
+    }
+
+    #[allow(clippy::result_large_err)]
+    #[tokio::main]
+    async fn get_object_acl(&self) -> Result<GetObjectAclOutput, RusotoError<GetObjectAclError>> {
+        let get_object_acl_input = GetObjectAclRequest {
+            bucket: self.bucket.name.to_string(),
+            expected_bucket_owner: self.expected_owner.clone(),
+            key: self.bucket.key.to_string(),
+            request_payer: self.request_payer(),
+            version_id: None,
+        };
+
+        self.timed(
+            "GetObjectAcl",
+            self.client.get_object_acl(get_object_acl_input),
+        )
+        .await
+    }
+
+    #[tokio::main]
+    async fn read_object(&self) -> Result<(GetObjectOutput, Vec<u8>), S3PathError> {
+        let mut object = match self
+            .timed(
+                "GetObject",
+                self.client.get_object(self.get_object_request()),
+            )
+            .await
+        {
+            Ok(object) => object,
+            Err(e) => return Err(process_get_object_error(e)),
+        };
+
+        let mut contents = vec![];
+        if let Some(body) = object.body.take() {
+            body.into_async_read()
+                .read_to_end(&mut contents)
+                .await
+                .map_err(|_| S3PathError::Unknown)?;
+        }
+
+        Ok((object, contents))
+    }
+
+    /// Reads the bytes from `start` to `end` of the object, both inclusive.
+    #[tokio::main]
+    pub async fn read_range(&self, start: u64, end: u64) -> Result<Vec<u8>, S3PathError> {
+        self.get_range(start, end).await
+    }
+
+    async fn get_range(&self, start: u64, end: u64) -> Result<Vec<u8>, S3PathError> {
+        let mut contents = vec![];
+        if let Some(body) = self.range_body(start, end).await? {
+            body.into_async_read()
+                .read_to_end(&mut contents)
+                .await
+                .map_err(|_| S3PathError::Unknown)?;
+        }
+
+        Ok(contents)
+    }
+
+    /// Reads the bytes of the object from `start` into `buf`, returning how many were read.
+    /// Fewer than `buf.len()` are read only when the object ends first.
+    #[tokio::main]
+    pub async fn read_range_into(&self, start: u64, buf: &mut [u8]) -> Result<usize, S3PathError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let end = start.saturating_add(buf.len() as u64 - 1);
+        let mut body = match self.range_body(start, end).await? {
+            Some(body) => body.into_async_read(),
+            None => return Ok(0),
+        };
+
+        let mut read = 0;
+        while read < buf.len() {
+            match body.read(&mut buf[read..]).await {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(_) => return Err(S3PathError::Unknown),
+            }
+        }
+
+        Ok(read)
+    }
+
+    /// The body of the bytes from `start` to `end` of the object, both inclusive, or `None`
+    /// when the range starts past the end of the object.
+    async fn range_body(&self, start: u64, end: u64) -> Result<Option<StreamingBody>, S3PathError> {
+        let get_object_request = GetObjectRequest {
+            range: Some(format!("bytes={}-{}", start, end)),
+            ..self.get_object_request()
+        };
+
+        match self
+            .timed("GetObject", self.client.get_object(get_object_request))
+            .await
+        {
+            Ok(object) => Ok(object.body),
+            // 416 Range Not Satisfiable: the range starts past the end of the object.
+            Err(RusotoError::Unknown(response)) if response.status.as_u16() == 416 => Ok(None),
+            Err(e) => Err(process_get_object_error(e)),
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    #[tokio::main]
+    async fn put_object(
+        &self,
+        put_object_request: PutObjectRequest,
+    ) -> Result<PutObjectOutput, RusotoError<PutObjectError>> {
+        self.timed("PutObject", self.client.put_object(put_object_request))
+            .await
+    }
+
+    #[allow(clippy::result_large_err)]
+    #[tokio::main]
+    async fn list_objects_v2(
+        &self,
+        continuation_token: Option<String>,
+        delimiter: Option<String>,
+    ) -> Result<ListObjectsV2Output, RusotoError<ListObjectsV2Error>> {
+        let list_objects_input = ListObjectsV2Request {
+            bucket: self.bucket.name.to_string(),
+            continuation_token,
+            delimiter,
+            // Keys are returned URL-encoded, so those with characters that XML cannot carry,
+            // such as control characters, come back intact.
+            encoding_type: Some("url".to_string()),
+            expected_bucket_owner: self.expected_owner.clone(),
+            fetch_owner: self.fetch_owner.then_some(true),
+            max_keys: self.max_keys,
+            // The bucket root is listed without a prefix.
+            prefix: Some(self.bucket.key.to_string()).filter(|prefix| !prefix.is_empty()),
+            request_payer: self.request_payer(),
+            start_after: None,
+        };
+
+        // A page that fails mid-walk, e.g. with a 503 SlowDown, is requested again after a
+        // backoff, so one throttled request does not abort the listing of a large prefix.
+        let mut attempt = 0;
+        loop {
+            match self
+                .timed(
+                    "ListObjectsV2",
+                    self.client.list_objects_v2(list_objects_input.clone()),
+                )
+                .await
+            {
+                Err(e) if attempt < LIST_PAGE_RETRIES && is_transient(&e) => {
+                    tokio::time::sleep(LIST_RETRY_DELAY * 2u32.pow(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result.map(decode_listing),
+            }
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    #[tokio::main]
+    async fn delete_objects_request(
+        &self,
+        delete_objects_request: DeleteObjectsRequest,
+    ) -> Result<DeleteObjectsOutput, RusotoError<DeleteObjectsError>> {
+        self.timed(
+            "DeleteObjects",
+            self.client.delete_objects(delete_objects_request),
+        )
+        .await
+    }
+
+    /// Writes `body` to the key of this service, the one whose metadata `options` may preserve.
+    pub fn write_to_object(
+        &self,
+        content_length: Option<i64>,
+        body: Option<StreamingBody>,
+        metadata: Option<HashMap<String, String>>,
+        options: &UploadOptions,
+    ) -> Result<PutObjectOutput, S3PathError> {
+        let metadata = self.upload_metadata_blocking(metadata, options)?;
+        let put_object_request =
+            self.put_object_request(content_length, body, &self.bucket.key, metadata, options);
+
+        match put_object_request {
+            PutObjectRequest {
+                content_length: None,
+                body: Some(_),
+                ..
+            } => self.upload_unsized(put_object_request, options.get_verify_etag()),
+            _ => match self.put_object(put_object_request) {
+                Ok(result) => Ok(result),
+                Err(e) => Err(process_error(Some(e), None, S3PathOp::PutObject)),
+            },
+        }
+    }
+
+    /// The user metadata to upload along with `metadata`: merged over that of the object being
+    /// replaced when `options` preserves it, with the metadata of `options` merged over both.
+    async fn upload_metadata(
+        &self,
+        metadata: Option<HashMap<String, String>>,
+        options: &UploadOptions,
+    ) -> Result<Option<HashMap<String, String>>, S3PathError> {
+        let mut merged = match options.get_preserve_metadata() {
+            true => match self.head(&self.bucket.key).await {
+                Ok(existing) => existing.metadata,
+                Err(e) => match process_error(Some(e), None, S3PathOp::HeadObject) {
+                    S3PathError::ObjectDoesNotExist => None,
+                    e => return Err(e),
+                },
+            },
+            false => None,
         };
 
-        self.client.get_object(get_object_input).await
+        for metadata in [metadata, options.get_metadata().cloned()]
+            .into_iter()
+            .flatten()
+        {
+            merged.get_or_insert_with(HashMap::new).extend(metadata);
+        }
+
+        Ok(merged)
     }
 
     #[tokio::main]
-    async fn put_object<P: ToString>(
+    async fn upload_metadata_blocking(
+        &self,
+        metadata: Option<HashMap<String, String>>,
+        options: &UploadOptions,
+    ) -> Result<Option<HashMap<String, String>>, S3PathError> {
+        self.upload_metadata(metadata, options).await
+    }
+
+    fn put_object_request<P: ToString>(
         &self,
         content_length: Option<i64>,
         body: Option<StreamingBody>,
         path: P,
         metadata: Option<HashMap<String, String>>,
-    ) -> Result<PutObjectOutput, RusotoError<PutObjectError>> {
-        let put_object_request = PutObjectRequest {
+        options: &UploadOptions,
+    ) -> PutObjectRequest {
+        let object_lock = options.get_object_lock();
+        PutObjectRequest {
             acl: None,
             body,
             bucket: self.bucket.name.to_string(),
             bucket_key_enabled: None,
-            cache_control: None,
-            content_disposition: None,
-            content_encoding: None,
+            cache_control: options.get_cache_control().map(str::to_string),
+            content_disposition: options.get_content_disposition().map(str::to_string),
+            content_encoding: options.get_content_encoding().map(str::to_string),
             content_language: None,
             content_length,
-            content_md5: None,
-            content_type: None,
-            expected_bucket_owner: None,
-            expires: None,
+            content_md5: options.content_md5.clone(),
+            content_type: options.content_type.clone(),
+            expected_bucket_owner: self.expected_owner.clone(),
+            expires: options.get_expires().map(str::to_string),
             grant_full_control: None,
             grant_read: None,
             grant_read_acp: None,
             grant_write_acp: None,
             key: path.to_string(),
             metadata,
-            object_lock_legal_hold_status: None,
-            object_lock_mode: None,
-            object_lock_retain_until_date: None,
-            request_payer: None,
+            object_lock_legal_hold_status: object_lock.map(ObjectLock::legal_hold_header),
+            object_lock_mode: object_lock.map(ObjectLock::mode_header),
+            object_lock_retain_until_date: object_lock.map(ObjectLock::retain_until_header),
+            request_payer: self.request_payer(),
             sse_customer_algorithm: None,
             sse_customer_key: None,
             sse_customer_key_md5: None,
@@ -126,48 +707,1887 @@ impl S3Service {
             storage_class: None,
             tagging: None,
             website_redirect_location: None,
-        };
+        }
+    }
+
+    #[tokio::main]
+    async fn upload_unsized(
+        &self,
+        mut request: PutObjectRequest,
+        verify_etag: bool,
+    ) -> Result<PutObjectOutput, S3PathError> {
+        let body = request.body.take().ok_or(S3PathError::Unknown)?;
+        let mut reader = Box::pin(body.into_async_read());
 
-        self.client.put_object(put_object_request).await
+        self.upload_reader(request, &mut reader, None, verify_etag, &mut |_, _| {})
+            .await
     }
 
-    pub fn write_to_object<P: ToString>(
+    /// Uploads the local file at `file` to the object, reporting the bytes sent to `progress`.
+    #[tokio::main]
+    pub async fn upload_file(
         &self,
-        content_length: Option<i64>,
-        body: Option<StreamingBody>,
-        path: P,
-        metadata: Option<HashMap<String, String>>,
+        file: &Path,
+        options: &UploadOptions,
+        progress: &mut dyn FnMut(u64, Option<u64>),
+    ) -> Result<u64, S3PathError> {
+        let mut file = File::open(file).await.map_err(|_| S3PathError::LocalFile)?;
+        let total = file
+            .metadata()
+            .await
+            .map_err(|_| S3PathError::LocalFile)?
+            .len();
+
+        let metadata = self.upload_metadata(None, options).await?;
+        let request = self.put_object_request(None, None, &self.bucket.key, metadata, options);
+        self.upload_reader(
+            request,
+            &mut file,
+            Some(total),
+            options.get_verify_etag(),
+            progress,
+        )
+        .await?;
+
+        Ok(total)
+    }
+
+    /// Streams the object of `from` into this one through both clients, so neither needs access
+    /// to the other's bucket. Only the parts in flight are held in memory. Returns the bytes copied.
+    ///
+    /// The user metadata, content-type and web headers of `source` are carried over, as
+    /// `CopyObject` does.
+    #[tokio::main]
+    pub async fn stream_from(
+        &self,
+        from: &S3Service,
+        source: &ObjectMetadata,
+    ) -> Result<u64, S3PathError> {
+        let body = from.get_object_stream().await?;
+        let mut reader = Box::pin(body.into_async_read());
+        let options = UploadOptions::preserving(source);
+        let request = self.put_object_request(
+            None,
+            None,
+            &self.bucket.key,
+            source.metadata.clone(),
+            &options,
+        );
+
+        let mut copied = 0;
+        self.upload_reader(
+            request,
+            &mut reader,
+            source.content_length.map(|length| length as u64),
+            options.get_verify_etag(),
+            &mut |sent, _| copied = sent,
+        )
+        .await?;
+
+        Ok(copied)
+    }
+
+    /// Uploads everything the blocking `reader` yields to the object, returning the number of
+    /// bytes sent. Only the parts in flight are held in memory.
+    #[tokio::main]
+    pub async fn upload_from_reader(
+        &self,
+        reader: &mut dyn Read,
+        options: &UploadOptions,
+    ) -> Result<u64, S3PathError> {
+        let metadata = self.upload_metadata(None, options).await?;
+        let request = self.put_object_request(None, None, &self.bucket.key, metadata, options);
+
+        let mut sent = 0;
+        self.upload_reader(
+            request,
+            &mut BlockingReader(reader),
+            None,
+            options.get_verify_etag(),
+            &mut |total_sent, _| sent = total_sent,
+        )
+        .await?;
+
+        Ok(sent)
+    }
+
+    /// Uploads everything `reader` yields. A body under the multipart threshold is buffered and
+    /// sent with a regular PUT, anything larger is streamed as a multipart upload.
+    ///
+    /// With `verify_etag`, the ETag S3 returns is checked against the one computed from the
+    /// bytes sent.
+    async fn upload_reader(
+        &self,
+        mut request: PutObjectRequest,
+        reader: &mut (impl AsyncRead + Unpin),
+        total: Option<u64>,
+        verify_etag: bool,
+        progress: &mut dyn FnMut(u64, Option<u64>),
     ) -> Result<PutObjectOutput, S3PathError> {
-        match self.put_object(content_length, body, path, metadata) {
-            Ok(result) => Ok(result),
-            Err(e) => Err(process_error(Some(e), None, S3PathOp::PutObject)),
+        let threshold = self.transfer.get_multipart_threshold();
+        let first_part = read_part(reader, threshold).await?;
+        if first_part.len() < threshold {
+            let sent = first_part.len() as u64;
+            let expected = ETag::of_contents(&first_part);
+            if locks_object(&request) && request.content_md5.is_none() {
+                request.content_md5 = Some(content_md5(&first_part));
+            }
+            request.content_length = Some(sent as i64);
+            request.body = Some(first_part.into());
+
+            let result = match self
+                .timed("PutObject", self.client.put_object(request))
+                .await
+            {
+                Ok(result) => result,
+                Err(e) => return Err(process_error(Some(e), None, S3PathOp::PutObject)),
+            };
+            progress(sent, total);
+            return match verify_etag {
+                true => verify_upload(result, &expected),
+                false => Ok(result),
+            };
+        }
+
+        let upload_id = self.create_multipart_upload(&request).await?;
+
+        let result = self
+            .upload_parts(&request, &upload_id, first_part, reader, total, progress)
+            .await;
+        if result.is_err() {
+            let abort_request = AbortMultipartUploadRequest {
+                bucket: self.bucket.name.to_string(),
+                expected_bucket_owner: self.expected_owner.clone(),
+                key: request.key.to_string(),
+                request_payer: self.request_payer(),
+                upload_id,
+            };
+            let _ = self
+                .timed(
+                    "AbortMultipartUpload",
+                    self.client.abort_multipart_upload(abort_request),
+                )
+                .await;
+        }
+
+        let (result, expected) = result?;
+        match verify_etag {
+            true => verify_upload(result, &expected),
+            false => Ok(result),
         }
     }
 
-    pub fn ensure_object_exists(&self) -> Result<bool, S3PathError> {
-        match self.object_exists() {
-            Ok(_) => Ok(true),
-            Err(e) => Err(process_error(Some(e), None, S3PathOp::HeadObject)),
+    /// Uploads `part` as part `part_number`, returning it along with its size. Sends the part's
+    /// Content-MD5 when `with_md5` is set, which S3 requires for Object Lock uploads.
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: i64,
+        part: Vec<u8>,
+        with_md5: bool,
+    ) -> Result<(CompletedPart, u64), S3PathError> {
+        let part_size = part.len() as u64;
+        let upload_part_request = UploadPartRequest {
+            content_md5: with_md5.then(|| content_md5(&part)),
+            body: Some(part.into()),
+            bucket: self.bucket.name.to_string(),
+            expected_bucket_owner: self.expected_owner.clone(),
+            key: key.to_string(),
+            part_number,
+            request_payer: self.request_payer(),
+            upload_id: upload_id.to_string(),
+            ..Default::default()
+        };
+
+        match self
+            .timed("UploadPart", self.client.upload_part(upload_part_request))
+            .await
+        {
+            Ok(output) => Ok((
+                CompletedPart {
+                    e_tag: output.e_tag,
+                    part_number: Some(part_number),
+                },
+                part_size,
+            )),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::MultipartUpload)),
         }
     }
 
-    pub fn get_object_body(&self) -> Result<Option<StreamingBody>, S3PathError> {
-        match self.get_object() {
-            Ok(body) => Ok(body.body),
-            Err(e) => Err(process_error(Some(e), None, S3PathOp::GetObject)),
+    /// Starts a multipart upload writing the object described by `request`, returning its id.
+    async fn create_multipart_upload(
+        &self,
+        request: &PutObjectRequest,
+    ) -> Result<String, S3PathError> {
+        let create_request = CreateMultipartUploadRequest {
+            bucket: request.bucket.to_string(),
+            cache_control: request.cache_control.clone(),
+            content_disposition: request.content_disposition.clone(),
+            content_encoding: request.content_encoding.clone(),
+            content_type: request.content_type.clone(),
+            expected_bucket_owner: request.expected_bucket_owner.clone(),
+            expires: request.expires.clone(),
+            key: request.key.to_string(),
+            metadata: request.metadata.clone(),
+            object_lock_legal_hold_status: request.object_lock_legal_hold_status.clone(),
+            object_lock_mode: request.object_lock_mode.clone(),
+            object_lock_retain_until_date: request.object_lock_retain_until_date.clone(),
+            request_payer: request.request_payer.clone(),
+            ..Default::default()
+        };
+
+        match self
+            .timed(
+                "CreateMultipartUpload",
+                self.client.create_multipart_upload(create_request),
+            )
+            .await
+        {
+            Ok(output) => output.upload_id.ok_or(S3PathError::Unknown),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::MultipartUpload)),
         }
     }
 
-    pub fn get_object_metadata(&self) -> Result<ObjectMetadata, S3PathError> {
-        match self.get_object() {
-            Ok(object) => Ok(ObjectMetadata {
-                content_type: object.content_type.unwrap(),
-                content_length: object.content_length,
-                e_tag: object.e_tag.unwrap(),
-                last_modified: object.last_modified.unwrap(),
-                metadata: object.metadata,
-            }),
-            Err(e) => Err(process_error(Some(e), None, S3PathOp::GetObject)),
+    /// Copies `from`, described by `source`, to the object server-side with `UploadPartCopy`,
+    /// which unlike `CopyObject` is not limited to 5 GiB.
+    ///
+    /// A failed copy leaves its multipart upload in place. The next copy to the same key resumes
+    /// it, copying only the missing parts, if it copies the same version of the same source with
+    /// the same part size. Any other upload of the key is aborted and the copy starts over.
+    ///
+    /// S3 does not return the metadata of an unfinished upload, so what each upload copies is
+    /// recorded in a file of the temporary directory, and only uploads started on this machine
+    /// are resumed.
+    #[tokio::main]
+    pub async fn multipart_copy_from(
+        &self,
+        from: &S3Service,
+        source: &ObjectMetadata,
+        options: &UploadOptions,
+        progress: &mut dyn FnMut(u64, Option<u64>),
+    ) -> Result<(), S3PathError> {
+        let length = source.content_length.unwrap_or_default() as u64;
+        let part_size = length
+            .div_ceil(MAX_PARTS)
+            .max(self.transfer.get_part_size() as u64);
+
+        let modified = DateTime::parse_from_rfc2822(&source.last_modified).ok();
+        let fingerprint = format!(
+            "{}/{}\n{}\n{}",
+            from.bucket.name,
+            from.bucket.key,
+            source.e_tag.as_str(),
+            part_size
+        );
+        let (upload_id, mut parts) = match self.resumable_upload(modified, &fingerprint).await? {
+            Some(upload_id) => {
+                let parts = self.list_parts(&upload_id).await?;
+                (upload_id, parts)
+            }
+            None => {
+                let request = self.put_object_request(
+                    None,
+                    None,
+                    &self.bucket.key,
+                    source.metadata.clone(),
+                    options,
+                );
+                let upload_id = self.create_multipart_upload(&request).await?;
+                // Without the record, the upload is neither resumed nor aborted by a later copy.
+                let _ = self.write_resume_record(&upload_id, &fingerprint).await;
+                (upload_id, vec![])
+            }
+        };
+
+        let copied = parts
+            .iter()
+            .map(|part| part.part_number.unwrap_or_default())
+            .collect::<Vec<i64>>();
+        let missing = (0..length)
+            .step_by(part_size as usize)
+            .enumerate()
+            .map(|(index, start)| (index as i64 + 1, start, (start + part_size).min(length) - 1))
+            .filter(|(part_number, _, _)| !copied.contains(part_number));
+
+        let copy_source = format!("{}/{}", from.bucket.name, from.bucket.key);
+        let copy_source = utf8_percent_encode(&copy_source, COPY_SOURCE).to_string();
+        let mut copies = stream::iter(missing)
+            .map(|(part_number, start, end)| {
+                let upload_part_copy_request = UploadPartCopyRequest {
+                    bucket: self.bucket.name.to_string(),
+                    copy_source: copy_source.to_string(),
+                    copy_source_if_match: Some(source.e_tag.as_str().to_string()),
+                    copy_source_range: Some(format!("bytes={}-{}", start, end)),
+                    expected_bucket_owner: self.expected_owner.clone(),
+                    expected_source_bucket_owner: from.expected_owner.clone(),
+                    key: self.bucket.key.to_string(),
+                    part_number,
+                    request_payer: self.request_payer(),
+                    upload_id: upload_id.to_string(),
+                    ..Default::default()
+                };
+                async move {
+                    match self
+                        .timed(
+                            "UploadPartCopy",
+                            self.client.upload_part_copy(upload_part_copy_request),
+                        )
+                        .await
+                    {
+                        Ok(output) => Ok(CompletedPart {
+                            e_tag: output.copy_part_result.and_then(|result| result.e_tag),
+                            part_number: Some(part_number),
+                        }),
+                        Err(e) => Err(process_error(Some(e), None, S3PathOp::MultipartUpload)),
+                    }
+                }
+            })
+            .buffer_unordered(self.transfer.get_concurrency());
+
+        let part_length = |part_number: i64| {
+            let start = (part_number as u64 - 1) * part_size;
+            (start + part_size).min(length).saturating_sub(start)
+        };
+        let mut sent = copied
+            .iter()
+            .map(|part_number| part_length(*part_number))
+            .sum();
+        progress(sent, Some(length));
+        while let Some(part) = copies.next().await {
+            let part = part?;
+            sent += part_length(part.part_number.unwrap_or_default());
+            progress(sent, Some(length));
+            parts.push(part);
+        }
+        parts.sort_by_key(|part| part.part_number);
+
+        let complete_request = CompleteMultipartUploadRequest {
+            bucket: self.bucket.name.to_string(),
+            expected_bucket_owner: self.expected_owner.clone(),
+            key: self.bucket.key.to_string(),
+            multipart_upload: Some(CompletedMultipartUpload { parts: Some(parts) }),
+            request_payer: self.request_payer(),
+            upload_id: upload_id.to_string(),
+        };
+        match self
+            .timed(
+                "CompleteMultipartUpload",
+                self.client.complete_multipart_upload(complete_request),
+            )
+            .await
+        {
+            Ok(_) => {
+                let _ = tokio::fs::remove_file(self.resume_record(&upload_id)).await;
+                Ok(())
+            }
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::MultipartUpload)),
+        }
+    }
+
+    /// The file recording what the multipart copy `upload_id` of the object copies.
+    fn resume_record(&self, upload_id: &str) -> PathBuf {
+        let upload = format!("{}/{}/{}", self.bucket.name, self.bucket.key, upload_id);
+
+        std::env::temp_dir()
+            .join("s3_fs-uploads")
+            .join(ETag::of_contents(upload.as_bytes()).value())
+    }
+
+    async fn write_resume_record(&self, upload_id: &str, fingerprint: &str) -> io::Result<()> {
+        let record = self.resume_record(upload_id);
+        if let Some(dir) = record.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+
+        tokio::fs::write(record, fingerprint).await
+    }
+
+    /// Finds an unfinished multipart copy of the object started after the source was last
+    /// `modified` and recorded with the same `fingerprint`, naming the source, its ETag and the
+    /// part size. The other copies recorded here are aborted, since their parts may hold other
+    /// bytes. Uploads without a record were started by someone else and are left alone.
+    async fn resumable_upload(
+        &self,
+        modified: Option<DateTime<FixedOffset>>,
+        fingerprint: &str,
+    ) -> Result<Option<String>, S3PathError> {
+        let mut resumable = None;
+        for upload in self.list_uploads().await? {
+            let (Some(key), Some(upload_id)) = (upload.key, upload.upload_id) else {
+                continue;
+            };
+            if key != self.bucket.key {
+                continue;
+            }
+
+            let record = self.resume_record(&upload_id);
+            let recorded = match tokio::fs::read_to_string(&record).await {
+                Ok(recorded) => recorded,
+                Err(_) => continue,
+            };
+            let initiated = upload
+                .initiated
+                .and_then(|initiated| DateTime::parse_from_rfc3339(&initiated).ok());
+            match (initiated, modified) {
+                (Some(initiated), Some(modified))
+                    if initiated > modified && recorded == fingerprint && resumable.is_none() =>
+                {
+                    resumable = Some(upload_id)
+                }
+                _ => {
+                    let _ = tokio::fs::remove_file(record).await;
+                    let abort_request = AbortMultipartUploadRequest {
+                        bucket: self.bucket.name.to_string(),
+                        expected_bucket_owner: self.expected_owner.clone(),
+                        key,
+                        request_payer: self.request_payer(),
+                        upload_id,
+                    };
+                    let _ = self
+                        .timed(
+                            "AbortMultipartUpload",
+                            self.client.abort_multipart_upload(abort_request),
+                        )
+                        .await;
+                }
+            }
+        }
+
+        Ok(resumable)
+    }
+
+    /// Lists the unfinished multipart uploads of keys starting with the key, page by page.
+    async fn list_uploads(&self) -> Result<Vec<MultipartUpload>, S3PathError> {
+        let mut uploads = vec![];
+        let mut markers = (None, None);
+
+        loop {
+            let list_request = ListMultipartUploadsRequest {
+                bucket: self.bucket.name.to_string(),
+                expected_bucket_owner: self.expected_owner.clone(),
+                key_marker: markers.0,
+                prefix: Some(self.bucket.key.to_string()),
+                upload_id_marker: markers.1,
+                ..Default::default()
+            };
+            let output = match self
+                .timed(
+                    "ListMultipartUploads",
+                    self.client.list_multipart_uploads(list_request),
+                )
+                .await
+            {
+                Ok(output) => output,
+                Err(e) => return Err(process_error(Some(e), None, S3PathOp::MultipartUpload)),
+            };
+
+            uploads.extend(output.uploads.unwrap_or_default());
+            match output.is_truncated {
+                Some(true) => markers = (output.next_key_marker, output.next_upload_id_marker),
+                _ => return Ok(uploads),
+            }
         }
     }
+
+    /// Lists the parts already uploaded to the multipart upload `upload_id`.
+    async fn list_parts(&self, upload_id: &str) -> Result<Vec<CompletedPart>, S3PathError> {
+        let mut parts = vec![];
+        let mut part_number_marker = None;
+
+        loop {
+            let list_request = ListPartsRequest {
+                bucket: self.bucket.name.to_string(),
+                expected_bucket_owner: self.expected_owner.clone(),
+                key: self.bucket.key.to_string(),
+                part_number_marker,
+                request_payer: self.request_payer(),
+                upload_id: upload_id.to_string(),
+                ..Default::default()
+            };
+            let output = match self
+                .timed("ListParts", self.client.list_parts(list_request))
+                .await
+            {
+                Ok(output) => output,
+                Err(e) => return Err(process_error(Some(e), None, S3PathOp::MultipartUpload)),
+            };
+
+            parts.extend(
+                output
+                    .parts
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|part| CompletedPart {
+                        e_tag: part.e_tag,
+                        part_number: part.part_number,
+                    }),
+            );
+
+            match output.is_truncated {
+                Some(true) => part_number_marker = output.next_part_number_marker,
+                _ => return Ok(parts),
+            }
+        }
+    }
+
+    /// Uploads `pending` followed by the rest of `reader` in parts, with up to the configured
+    /// concurrency in flight, and completes the upload `request` started.
+    async fn upload_parts(
+        &self,
+        request: &PutObjectRequest,
+        upload_id: &str,
+        mut pending: Vec<u8>,
+        reader: &mut (impl AsyncRead + Unpin),
+        total: Option<u64>,
+        progress: &mut dyn FnMut(u64, Option<u64>),
+    ) -> Result<(PutObjectOutput, ETag), S3PathError> {
+        let key = &request.key;
+        let with_md5 = locks_object(request);
+        let part_size = match total {
+            Some(total) => self
+                .transfer
+                .get_part_size()
+                .max(total.div_ceil(MAX_PARTS) as usize),
+            None => self.transfer.get_part_size(),
+        };
+        let mut parts = vec![];
+        let mut in_flight = FuturesUnordered::new();
+        let mut part_number = 0;
+        let mut read_all = false;
+        let mut sent = 0;
+        let mut e_tag = MultipartETag::default();
+
+        loop {
+            while !read_all && in_flight.len() < self.transfer.get_concurrency() {
+                let part = next_part(&mut pending, reader, part_size).await?;
+                if part.is_empty() {
+                    read_all = true;
+                    break;
+                }
+                part_number += 1;
+                e_tag.add_part(&part);
+                in_flight.push(self.upload_part(key, upload_id, part_number, part, with_md5));
+            }
+
+            match in_flight.next().await {
+                Some(part) => {
+                    let (part, part_size) = part?;
+                    parts.push(part);
+                    sent += part_size;
+                    progress(sent, total);
+                }
+                None => break,
+            }
+        }
+        parts.sort_by_key(|part| part.part_number);
+
+        let complete_request = CompleteMultipartUploadRequest {
+            bucket: self.bucket.name.to_string(),
+            expected_bucket_owner: self.expected_owner.clone(),
+            key: key.to_string(),
+            multipart_upload: Some(CompletedMultipartUpload { parts: Some(parts) }),
+            request_payer: self.request_payer(),
+            upload_id: upload_id.to_string(),
+        };
+        match self
+            .timed(
+                "CompleteMultipartUpload",
+                self.client.complete_multipart_upload(complete_request),
+            )
+            .await
+        {
+            Ok(output) => Ok((
+                PutObjectOutput {
+                    e_tag: output.e_tag,
+                    version_id: output.version_id,
+                    ..Default::default()
+                },
+                e_tag.finish(),
+            )),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::MultipartUpload)),
+        }
+    }
+
+    #[tokio::main]
+    pub async fn copy_from(
+        &self,
+        from: &S3Service,
+        directive: &MetadataDirective,
+    ) -> Result<CopyObjectOutput, S3PathError> {
+        self.copy_object_from(from, directive).await
+    }
+
+    pub async fn copy_object_from(
+        &self,
+        from: &S3Service,
+        directive: &MetadataDirective,
+    ) -> Result<CopyObjectOutput, S3PathError> {
+        self.copy_object_tagged(from, directive, &TaggingDirective::Copy)
+            .await
+    }
+
+    /// Like [S3Service::copy_from], also keeping or replacing the tags of the source as
+    /// `tagging` says.
+    #[tokio::main]
+    pub async fn copy_with_tagging(
+        &self,
+        from: &S3Service,
+        directive: &MetadataDirective,
+        tagging: &TaggingDirective,
+    ) -> Result<CopyObjectOutput, S3PathError> {
+        self.copy_object_tagged(from, directive, tagging).await
+    }
+
+    async fn copy_object_tagged(
+        &self,
+        from: &S3Service,
+        directive: &MetadataDirective,
+        tagging: &TaggingDirective,
+    ) -> Result<CopyObjectOutput, S3PathError> {
+        let (content_type, metadata) = match directive {
+            MetadataDirective::Copy => (None, None),
+            MetadataDirective::Replace {
+                content_type,
+                metadata,
+            } => (content_type.clone(), Some(metadata.clone())),
+        };
+
+        let copy_source = format!("{}/{}", from.bucket.name, from.bucket.key);
+        let copy_object_request = CopyObjectRequest {
+            bucket: self.bucket.name.to_string(),
+            content_type,
+            copy_source: utf8_percent_encode(&copy_source, COPY_SOURCE).to_string(),
+            expected_bucket_owner: self.expected_owner.clone(),
+            expected_source_bucket_owner: from.expected_owner.clone(),
+            key: self.bucket.key.to_string(),
+            metadata,
+            metadata_directive: Some(directive.as_header().to_string()),
+            request_payer: self.request_payer(),
+            tagging: tagging.tagging(),
+            tagging_directive: Some(tagging.as_header().to_string()),
+            ..Default::default()
+        };
+
+        match self
+            .timed("CopyObject", self.client.copy_object(copy_object_request))
+            .await
+        {
+            Ok(result) => Ok(result),
+            // rusoto has no variant for a missing source, which S3 reports as NoSuchKey.
+            Err(RusotoError::Unknown(response))
+                if String::from_utf8_lossy(&response.body).contains("<Code>NoSuchKey</Code>") =>
+            {
+                Err(S3PathError::ObjectDoesNotExist)
+            }
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::CopyObject)),
+        }
+    }
+
+    /// Replaces the user metadata of the object by copying it onto itself, keeping its
+    /// content-type, web headers and storage class.
+    #[tokio::main]
+    pub async fn set_metadata(
+        &self,
+        metadata: HashMap<String, String>,
+    ) -> Result<CopyObjectOutput, S3PathError> {
+        let object = match self.head(&self.bucket.key).await {
+            Ok(object) => object,
+            Err(e) => return Err(process_error(Some(e), None, S3PathOp::HeadObject)),
+        };
+
+        let copy_source = format!("{}/{}", self.bucket.name, self.bucket.key);
+        let copy_object_request = CopyObjectRequest {
+            bucket: self.bucket.name.to_string(),
+            cache_control: object.cache_control,
+            content_disposition: object.content_disposition,
+            content_encoding: object.content_encoding,
+            content_language: object.content_language,
+            content_type: object.content_type,
+            copy_source: utf8_percent_encode(&copy_source, COPY_SOURCE).to_string(),
+            copy_source_if_match: object.e_tag,
+            expected_bucket_owner: self.expected_owner.clone(),
+            expected_source_bucket_owner: self.expected_owner.clone(),
+            expires: object.expires,
+            key: self.bucket.key.to_string(),
+            metadata: Some(metadata),
+            metadata_directive: Some("REPLACE".to_string()),
+            request_payer: self.request_payer(),
+            storage_class: object.storage_class,
+            website_redirect_location: object.website_redirect_location,
+            ..Default::default()
+        };
+
+        match self
+            .timed("CopyObject", self.client.copy_object(copy_object_request))
+            .await
+        {
+            Ok(result) => Ok(result),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::CopyObject)),
+        }
+    }
+
+    /// Builds a URL granting a GET of the object for `expires_in`, with the response headers
+    /// replaced by `overrides`. It is signed with the credentials the client signs with.
+    #[tokio::main]
+    pub async fn presigned_get(
+        &self,
+        expires_in: Duration,
+        overrides: &ResponseOverrides,
+    ) -> Result<String, S3PathError> {
+        let core = self.core()?;
+        let credentials = core
+            .credentials
+            .credentials()
+            .await
+            .map_err(|_| S3PathError::ExpiredToken)?;
+
+        let get_object_request = GetObjectRequest {
+            response_cache_control: overrides.get_cache_control().map(str::to_string),
+            response_content_disposition: overrides.get_content_disposition().map(str::to_string),
+            response_content_encoding: overrides.get_content_encoding().map(str::to_string),
+            response_content_language: overrides.get_content_language().map(str::to_string),
+            response_content_type: overrides.get_content_type().map(str::to_string),
+            response_expires: overrides.get_expires().map(str::to_string),
+            ..self.get_object_request()
+        };
+
+        Ok(get_object_request.get_presigned_url(
+            &core.region,
+            &credentials,
+            &PreSignedRequestOption { expires_in },
+        ))
+    }
+
+    pub fn write_redirect<P: ToString>(
+        &self,
+        path: P,
+        location: String,
+    ) -> Result<PutObjectOutput, S3PathError> {
+        let put_object_request = PutObjectRequest {
+            bucket: self.bucket.name.to_string(),
+            content_length: Some(0),
+            expected_bucket_owner: self.expected_owner.clone(),
+            key: path.to_string(),
+            request_payer: self.request_payer(),
+            website_redirect_location: Some(location),
+            ..Default::default()
+        };
+
+        match self.put_object(put_object_request) {
+            Ok(result) => Ok(result),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::PutObject)),
+        }
+    }
+
+    pub fn get_redirect_location(&self) -> Result<Option<String>, S3PathError> {
+        match self.object_exists() {
+            Ok(object) => Ok(object.website_redirect_location),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::HeadObject)),
+        }
+    }
+
+    pub fn key_exists<P: ToString>(&self, path: P) -> Result<bool, S3PathError> {
+        match self.head_object(path) {
+            Ok(_) => Ok(true),
+            Err(e) => match process_error(Some(e), None, S3PathOp::HeadObject) {
+                S3PathError::ObjectDoesNotExist => Ok(false),
+                error => Err(error),
+            },
+        }
+    }
+
+    /// Runs `op` on a service pointed at each of `keys` in the same bucket, with at most
+    /// `concurrency` operations in flight, returning the result for each key in the order of `keys`.
+    ///
+    /// A failed operation does not stop the others, so callers decide what a failure means.
+    pub(crate) async fn batch<T, F, Fut>(
+        &self,
+        keys: Vec<String>,
+        concurrency: usize,
+        op: F,
+    ) -> Vec<(String, Result<T, S3PathError>)>
+    where
+        F: Fn(S3Service) -> Fut,
+        Fut: Future<Output = Result<T, S3PathError>>,
+    {
+        stream::iter(keys)
+            .map(|key| {
+                let result = op(self.with_key(&key));
+                async move { (key, result.await) }
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    #[tokio::main]
+    pub async fn objects_exist(
+        &self,
+        keys: &[String],
+    ) -> Result<HashMap<String, bool>, S3PathError> {
+        let results = self
+            .batch(
+                keys.to_vec(),
+                MAX_CONCURRENT_REQUESTS,
+                |service| async move {
+                    match service.head(&service.bucket.key).await {
+                        Ok(_) => Ok(true),
+                        Err(e) => match process_error(Some(e), None, S3PathOp::HeadObject) {
+                            S3PathError::ObjectDoesNotExist => Ok(false),
+                            error => Err(error),
+                        },
+                    }
+                },
+            )
+            .await;
+
+        results
+            .into_iter()
+            .map(|(key, result)| result.map(|exists| (key, exists)))
+            .collect()
+    }
+
+    async fn get_tags(&self) -> Result<HashMap<String, String>, S3PathError> {
+        let request = GetObjectTaggingRequest {
+            bucket: self.bucket.name.to_string(),
+            expected_bucket_owner: self.expected_owner.clone(),
+            key: self.bucket.key.to_string(),
+            request_payer: self.request_payer(),
+            ..Default::default()
+        };
+
+        match self
+            .timed("GetObjectTagging", self.client.get_object_tagging(request))
+            .await
+        {
+            Ok(output) => Ok(output
+                .tag_set
+                .into_iter()
+                .map(|tag| (tag.key, tag.value))
+                .collect()),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::GetObjectTagging)),
+        }
+    }
+
+    /// Returns those of `keys` whose tag set has `tag_key` set to `tag_value`, fetching the tags
+    /// of up to `MAX_CONCURRENT_REQUESTS` objects at a time.
+    #[tokio::main]
+    pub async fn keys_tagged(
+        &self,
+        keys: Vec<String>,
+        tag_key: &str,
+        tag_value: &str,
+    ) -> Result<Vec<String>, S3PathError> {
+        let results = self
+            .batch(keys, MAX_CONCURRENT_REQUESTS, |service| async move {
+                service.get_tags().await
+            })
+            .await;
+
+        let mut tagged = Vec::new();
+        for (key, tags) in results {
+            if tags?.get(tag_key).map(String::as_str) == Some(tag_value) {
+                tagged.push(key);
+            }
+        }
+
+        Ok(tagged)
+    }
+
+    pub fn get_object_body(&self) -> Result<Option<StreamingBody>, S3PathError> {
+        match self.get_object() {
+            Ok(body) => Ok(body.body),
+            Err(e) => Err(process_get_object_error(e)),
+        }
+    }
+
+    /// Downloads the object to the local file at `file`, reporting the bytes received to `progress`.
+    #[tokio::main]
+    pub async fn download_file(
+        &self,
+        file: &Path,
+        progress: &mut dyn FnMut(u64, Option<u64>),
+    ) -> Result<u64, S3PathError> {
+        let object = match self
+            .timed(
+                "GetObject",
+                self.client.get_object(self.get_object_request()),
+            )
+            .await
+        {
+            Ok(object) => object,
+            Err(e) => return Err(process_get_object_error(e)),
+        };
+        let total = object.content_length.map(|length| length as u64);
+
+        let mut file = File::create(file)
+            .await
+            .map_err(|_| S3PathError::LocalFile)?;
+        let mut received = 0;
+        if let Some(mut body) = object.body {
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk.map_err(|_| S3PathError::Unknown)?;
+                file.write_all(&chunk)
+                    .await
+                    .map_err(|_| S3PathError::LocalFile)?;
+                received += chunk.len() as u64;
+                progress(received, total);
+            }
+        }
+        file.flush().await.map_err(|_| S3PathError::LocalFile)?;
+
+        Ok(received)
+    }
+
+    /// Writes the body of the object to `writer` chunk by chunk as it is received, returning
+    /// the number of bytes written.
+    #[tokio::main]
+    pub async fn copy_to_writer(&self, writer: &mut dyn Write) -> Result<u64, S3PathError> {
+        let mut body = self.get_object_stream().await?;
+
+        let mut written = 0;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|_| S3PathError::Unknown)?;
+            writer
+                .write_all(&chunk)
+                .map_err(|_| S3PathError::LocalFile)?;
+            written += chunk.len() as u64;
+        }
+        writer.flush().map_err(|_| S3PathError::LocalFile)?;
+
+        Ok(written)
+    }
+
+    /// Downloads the object to the local file at `file` as `parts` ranged GETs sent concurrently,
+    /// each written at its offset in the file.
+    #[tokio::main]
+    pub async fn download_file_parallel(
+        &self,
+        file: &Path,
+        parts: u64,
+    ) -> Result<u64, S3PathError> {
+        let length = match self.head(&self.bucket.key).await {
+            Ok(object) => object.content_length.unwrap_or_default() as u64,
+            Err(e) => return Err(process_error(Some(e), None, S3PathOp::HeadObject)),
+        };
+
+        File::create(file)
+            .await
+            .map_err(|_| S3PathError::LocalFile)?
+            .set_len(length)
+            .await
+            .map_err(|_| S3PathError::LocalFile)?;
+
+        let part_size = length.div_ceil(parts.max(1)).max(1);
+        let ranges = (0..length)
+            .step_by(part_size as usize)
+            .map(|start| (start, (start + part_size).min(length) - 1));
+        let written = stream::iter(ranges)
+            .map(|(start, end)| self.download_range(file, start, end))
+            .buffer_unordered(self.transfer.get_concurrency())
+            .collect::<Vec<Result<u64, S3PathError>>>()
+            .await
+            .into_iter()
+            .sum::<Result<u64, S3PathError>>()?;
+
+        match written == length {
+            true => Ok(written),
+            false => Err(S3PathError::ShortRead),
+        }
+    }
+
+    /// Writes the bytes from `start` to `end` of the object, both inclusive, at the same offset of `file`.
+    ///
+    /// The file is opened again for each range, since handles cloned from one another share a
+    /// single offset, which the ranges written concurrently would move under each other.
+    async fn download_range(&self, file: &Path, start: u64, end: u64) -> Result<u64, S3PathError> {
+        let get_object_request = GetObjectRequest {
+            range: Some(format!("bytes={}-{}", start, end)),
+            ..self.get_object_request()
+        };
+        let object = match self
+            .timed("GetObject", self.client.get_object(get_object_request))
+            .await
+        {
+            Ok(object) => object,
+            Err(e) => return Err(process_get_object_error(e)),
+        };
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(file)
+            .await
+            .map_err(|_| S3PathError::LocalFile)?;
+        file.seek(SeekFrom::Start(start))
+            .await
+            .map_err(|_| S3PathError::LocalFile)?;
+        let mut written = 0;
+        if let Some(mut body) = object.body {
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk.map_err(|_| S3PathError::Unknown)?;
+                file.write_all(&chunk)
+                    .await
+                    .map_err(|_| S3PathError::LocalFile)?;
+                written += chunk.len() as u64;
+            }
+        }
+        file.flush().await.map_err(|_| S3PathError::LocalFile)?;
+
+        Ok(written)
+    }
+
+    pub async fn get_object_stream(&self) -> Result<StreamingBody, S3PathError> {
+        match self
+            .timed(
+                "GetObject",
+                self.client.get_object(self.get_object_request()),
+            )
+            .await
+        {
+            Ok(object) => Ok(object.body.unwrap_or_else(|| Vec::new().into())),
+            Err(e) => Err(process_get_object_error(e)),
+        }
+    }
+
+    pub fn read_contents(&self, decode: bool) -> Result<Vec<u8>, S3PathError> {
+        let (object, contents) = self.read_object()?;
+
+        match object.content_encoding {
+            Some(encoding) if decode && encoding.contains("gzip") => gzip::decompress(&contents),
+            _ => Ok(contents),
+        }
+    }
+
+    pub fn get_acl(&self) -> Result<Acl, S3PathError> {
+        match self.get_object_acl() {
+            Ok(output) => {
+                let owner_id = output.owner.and_then(|owner| owner.id);
+                let grants = output
+                    .grants
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|grant| {
+                        let grantee = grant.grantee?;
+                        Some(Grant {
+                            grantee: grantee.id.or(grantee.email_address).or(grantee.uri)?,
+                            permission: grant.permission?,
+                        })
+                    })
+                    .collect();
+
+                Ok(Acl::from_grants(owner_id.as_deref(), grants))
+            }
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::GetObjectAcl)),
+        }
+    }
+
+    pub fn get_file_type(&self) -> Result<S3ObjectType, S3PathError> {
+        match self.object_exists() {
+            Ok(object) => {
+                let is_marker = self.bucket.key.ends_with('/')
+                    || object
+                        .content_type
+                        .is_some_and(|content_type| content_type.contains(DIRECTORY_CONTENT_TYPE));
+                match is_marker {
+                    true => Ok(S3ObjectType::Directory),
+                    false => Ok(S3ObjectType::File),
+                }
+            }
+            Err(e) => match process_error(Some(e), None, S3PathOp::HeadObject) {
+                S3PathError::ObjectDoesNotExist => match self.marker_exists()? {
+                    true => Ok(S3ObjectType::Directory),
+                    false => self.get_prefix_type(),
+                },
+                error => Err(error),
+            },
+        }
+    }
+
+    /// Returns `true` if any object lives under the key taken as a directory, not counting the
+    /// `key/` marker itself. A single listing of one key is made, starting after the marker.
+    #[tokio::main]
+    pub async fn has_objects_under(&self) -> Result<bool, S3PathError> {
+        let prefix = sync::dir_prefix(&self.bucket.key);
+        let list_objects_input = ListObjectsV2Request {
+            bucket: self.bucket.name.to_string(),
+            expected_bucket_owner: self.expected_owner.clone(),
+            max_keys: Some(1),
+            prefix: Some(prefix.to_string()),
+            request_payer: self.request_payer(),
+            start_after: Some(prefix),
+            ..Default::default()
+        };
+
+        match self
+            .timed(
+                "ListObjectsV2",
+                self.client.list_objects_v2(list_objects_input),
+            )
+            .await
+        {
+            Ok(output) => Ok(!output.contents.unwrap_or_default().is_empty()),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::ListObjectsV2)),
+        }
+    }
+
+    /// Returns `true` if the marker object of the directory at the key exists, following
+    /// the configured [DirectoryMarker] convention.
+    pub fn marker_exists(&self) -> Result<bool, S3PathError> {
+        match self.directory_marker.marker_key(&self.bucket.key) {
+            Some(marker) if marker != self.bucket.key => self.key_exists(marker),
+            _ => Ok(false),
+        }
+    }
+
+    /// Creates the directory at the key by writing its marker object, returning the path of
+    /// the directory. An existing marker is an error unless `exist_ok` is set.
+    ///
+    /// With [DirectoryMarker::None] nothing is written, as the directory appears with its first object.
+    pub fn create_dir(&self, exist_ok: bool) -> Result<S3Path, S3PathError> {
+        let key = sync::dir_prefix(&self.bucket.key);
+        let dir = S3Path::from_service(self.with_key(&key));
+        let marker = match self.directory_marker.marker_key(&key) {
+            Some(marker) => marker,
+            None => return Ok(dir),
+        };
+
+        match self.key_exists(&marker)? {
+            true if exist_ok => return Ok(dir),
+            true => return Err(S3PathError::ObjectAlreadyExists),
+            false => {}
+        }
+
+        let request = PutObjectRequest {
+            content_type: self.marker_content_type.clone(),
+            ..self.put_object_request(Some(0), None, marker, None, &UploadOptions::default())
+        };
+        if let Err(e) = self.put_object(request) {
+            return Err(process_error(Some(e), None, S3PathOp::PutObject));
+        }
+
+        Ok(dir)
+    }
+
+    /// Creates the directory at the key and each of its missing parents, leaving existing
+    /// markers as they are.
+    pub fn create_dir_all(&self) -> Result<S3Path, S3PathError> {
+        let key = sync::dir_prefix(&self.bucket.key);
+        let mut prefix = String::new();
+
+        for component in key.split_terminator('/') {
+            prefix = format!("{}{}/", prefix, component);
+            self.with_key(&prefix).create_dir(true)?;
+        }
+
+        Ok(S3Path::from_service(self.with_key(key)))
+    }
+
+    fn get_prefix_type(&self) -> Result<S3ObjectType, S3PathError> {
+        let (listing, _) = self
+            .with_key(sync::dir_prefix(&self.bucket.key))
+            .list_page(None)?;
+
+        match listing.files().is_empty() && listing.dirs().is_empty() {
+            true => Err(S3PathError::ObjectDoesNotExist),
+            false => Ok(S3ObjectType::Directory),
+        }
+    }
+
+    pub fn get_object_metadata(&self) -> Result<ObjectMetadata, S3PathError> {
+        match self.object_exists() {
+            Ok(object) => Ok(ObjectMetadata {
+                content_type: object.content_type.unwrap_or_default(),
+                content_length: object.content_length,
+                e_tag: object.e_tag.unwrap_or_default().into(),
+                last_modified: object.last_modified.unwrap_or_default(),
+                metadata: object.metadata,
+                cache_control: object.cache_control,
+                content_disposition: object.content_disposition,
+                content_encoding: object.content_encoding,
+                expires: object.expires,
+                storage_class: object.storage_class,
+                restore: object.restore,
+                server_side_encryption: object.server_side_encryption,
+            }),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::HeadObject)),
+        }
+    }
+
+    pub fn head_etag(&self) -> Result<Option<String>, S3PathError> {
+        match self.object_exists() {
+            Ok(object) => Ok(object.e_tag),
+            Err(e) => match process_error(Some(e), None, S3PathOp::HeadObject) {
+                S3PathError::ObjectDoesNotExist => Ok(None),
+                e => Err(e),
+            },
+        }
+    }
+
+    #[tokio::main]
+    pub async fn restore_object(&self, days: i32, tier: Tier) -> Result<(), S3PathError> {
+        let restore_object_request = RestoreObjectRequest {
+            bucket: self.bucket.name.to_string(),
+            expected_bucket_owner: self.expected_owner.clone(),
+            key: self.bucket.key.to_string(),
+            request_payer: self.request_payer(),
+            restore_request: Some(RestoreRequest {
+                days: Some(i64::from(days)),
+                glacier_job_parameters: Some(GlacierJobParameters {
+                    tier: tier.as_header().to_string(),
+                }),
+                ..Default::default()
+            }),
+            version_id: None,
+        };
+
+        match self
+            .timed(
+                "RestoreObject",
+                self.client.restore_object(restore_object_request),
+            )
+            .await
+        {
+            Ok(_) => Ok(()),
+            // S3 answers 409 RestoreAlreadyInProgress when the object is already being restored.
+            Err(RusotoError::Unknown(response)) if response.status.as_u16() == 409 => Ok(()),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::RestoreObject)),
+        }
+    }
+
+    #[tokio::main]
+    pub async fn create_bucket(&self, region: Region) -> Result<(), S3PathError> {
+        // us-east-1 is the default location and is rejected as an explicit constraint.
+        let create_bucket_configuration = match region {
+            Region::UsEast1 => None,
+            _ => Some(CreateBucketConfiguration {
+                location_constraint: Some(region.name().to_string()),
+            }),
+        };
+        let create_bucket_request = CreateBucketRequest {
+            bucket: self.bucket.name.to_string(),
+            create_bucket_configuration,
+            ..Default::default()
+        };
+
+        let client = S3Client::new_with_client(self.core()?.client.clone(), region);
+        match self
+            .timed("CreateBucket", client.create_bucket(create_bucket_request))
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(RusotoError::Service(CreateBucketError::BucketAlreadyOwnedByYou(_))) => {
+                Err(S3PathError::BucketAlreadyOwned)
+            }
+            Err(RusotoError::Service(CreateBucketError::BucketAlreadyExists(_))) => {
+                Err(S3PathError::BucketNameTaken)
+            }
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::CreateBucket)),
+        }
+    }
+
+    #[tokio::main]
+    pub async fn delete_bucket(&self) -> Result<(), S3PathError> {
+        let delete_bucket_request = DeleteBucketRequest {
+            bucket: self.bucket.name.to_string(),
+            expected_bucket_owner: self.expected_owner.clone(),
+        };
+
+        match self
+            .timed(
+                "DeleteBucket",
+                self.client.delete_bucket(delete_bucket_request),
+            )
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(RusotoError::Unknown(response)) if response.status.as_u16() == 409 => {
+                Err(S3PathError::BucketNotEmpty)
+            }
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::DeleteBucket)),
+        }
+    }
+
+    pub fn list_page(
+        &self,
+        continuation_token: Option<String>,
+    ) -> Result<(Listing, Option<String>), S3PathError> {
+        // Without a delimiter, S3 returns every key under the prefix and no common prefixes.
+        let delimiter = match self.recursive {
+            true => None,
+            false => Some("/".to_string()),
+        };
+        let output = match self.list_objects_v2(continuation_token, delimiter) {
+            Ok(output) => output,
+            Err(e) => return Err(process_error(Some(e), None, S3PathOp::ListObjectsV2)),
+        };
+
+        let mut files = vec![];
+        let mut dirs = vec![];
+
+        let mut marked_dirs = vec![];
+        let mut owners = HashMap::new();
+
+        for object in output.contents.unwrap_or_default() {
+            if let (Some(key), Some(owner)) = (&object.key, object.owner) {
+                let owner = ObjectOwner {
+                    id: owner.id,
+                    display_name: owner.display_name,
+                };
+                owners.insert(key.to_string(), owner);
+            }
+
+            match object.key {
+                Some(key) if key != self.bucket.key => {
+                    match self.directory_marker.directory_of(&key) {
+                        Some(dir) if !key.ends_with('/') => marked_dirs.push(dir),
+                        _ => files.push(S3Path::from_service(self.with_key(key))),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut prefixes = output
+            .common_prefixes
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|common_prefix| common_prefix.prefix)
+            .collect::<Vec<String>>();
+        // A Hadoop marker names a directory that may also be listed as a common prefix.
+        for dir in marked_dirs {
+            if !prefixes.contains(&dir) {
+                prefixes.push(dir);
+            }
+        }
+
+        for prefix in prefixes {
+            dirs.push(S3Path::from_service(self.with_key(prefix)))
+        }
+
+        Ok((
+            Listing::new(files, dirs, self.bucket.key.to_string()).with_owners(owners),
+            output.next_continuation_token,
+        ))
+    }
+
+    pub fn list_objects(&self) -> Result<Listing, S3PathError> {
+        let (mut listing, mut continuation_token) = self.list_page(None)?;
+
+        while let Some(token) = continuation_token {
+            let (page, next_token) = self.list_page(Some(token))?;
+            listing.extend(page);
+            continuation_token = next_token;
+        }
+
+        Ok(listing)
+    }
+
+    /// Lists the keys of the common prefixes directly under the key, across all pages.
+    pub fn list_dirs(&self) -> Result<Vec<String>, S3PathError> {
+        let mut dirs = vec![];
+        let mut continuation_token = None;
+
+        loop {
+            let output = match self.list_objects_v2(continuation_token, Some("/".to_string())) {
+                Ok(output) => output,
+                Err(e) => return Err(process_error(Some(e), None, S3PathOp::ListObjectsV2)),
+            };
+
+            dirs.extend(
+                output
+                    .common_prefixes
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|common_prefix| common_prefix.prefix),
+            );
+
+            match output.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => return Ok(dirs),
+            }
+        }
+    }
+
+    pub fn list_all_objects(&self) -> Result<Vec<Object>, S3PathError> {
+        self.list_all(None)
+    }
+
+    /// Returns the metadata of each object directly under the key, as carried by the listing.
+    ///
+    /// Listings have no content-type or user metadata, so those are left empty, and
+    /// subdirectories are skipped as they have no metadata at all.
+    pub fn stat_objects(&self) -> Result<Vec<(S3Path, ObjectMetadata)>, S3PathError> {
+        Ok(self
+            .list_all(Some("/".to_string()))?
+            .into_iter()
+            .filter(|object| object.key.as_ref() != Some(&self.bucket.key))
+            .filter_map(|object| {
+                let last_modified = object.last_modified.unwrap_or_default();
+                let metadata = ObjectMetadata {
+                    content_type: String::new(),
+                    content_length: object.size,
+                    e_tag: object.e_tag.unwrap_or_default().into(),
+                    // Listings date objects in RFC 3339, HEAD requests in RFC 2822.
+                    last_modified: DateTime::parse_from_rfc3339(&last_modified)
+                        .map(|date| date.to_rfc2822())
+                        .unwrap_or(last_modified),
+                    metadata: None,
+                    cache_control: None,
+                    content_disposition: None,
+                    content_encoding: None,
+                    expires: None,
+                    storage_class: object.storage_class,
+                    restore: None,
+                    server_side_encryption: None,
+                };
+                Some((S3Path::from_service(self.with_key(object.key?)), metadata))
+            })
+            .collect())
+    }
+
+    fn list_all(&self, delimiter: Option<String>) -> Result<Vec<Object>, S3PathError> {
+        let mut objects = vec![];
+        let mut continuation_token = None;
+
+        loop {
+            let output = match self.list_objects_v2(continuation_token, delimiter.clone()) {
+                Ok(output) => output,
+                Err(e) => return Err(process_error(Some(e), None, S3PathOp::ListObjectsV2)),
+            };
+
+            objects.extend(output.contents.unwrap_or_default());
+
+            match output.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => return Ok(objects),
+            }
+        }
+    }
+
+    /// Lists every version and delete marker of the objects whose keys start with the key,
+    /// across all pages, ordered by key and then from the newest to the oldest.
+    #[tokio::main]
+    pub async fn list_object_versions(&self) -> Result<Vec<ObjectVersion>, S3PathError> {
+        let mut versions = vec![];
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+
+        loop {
+            let list_request = ListObjectVersionsRequest {
+                bucket: self.bucket.name.to_string(),
+                expected_bucket_owner: self.expected_owner.clone(),
+                key_marker,
+                max_keys: self.max_keys,
+                prefix: Some(self.bucket.key.to_string()).filter(|prefix| !prefix.is_empty()),
+                version_id_marker,
+                ..Default::default()
+            };
+            let output = match self
+                .timed(
+                    "ListObjectVersions",
+                    self.client.list_object_versions(list_request),
+                )
+                .await
+            {
+                Ok(output) => output,
+                Err(e) => return Err(process_error(Some(e), None, S3PathOp::ListObjectVersions)),
+            };
+
+            let mut page = vec![];
+            for version in output.versions.unwrap_or_default() {
+                if let (Some(key), Some(version_id)) = (version.key, version.version_id) {
+                    page.push(ObjectVersion {
+                        key,
+                        version_id,
+                        is_latest: version.is_latest.unwrap_or_default(),
+                        is_delete_marker: false,
+                        last_modified: version.last_modified,
+                        size: version.size,
+                        e_tag: version.e_tag.map(ETag::from),
+                    });
+                }
+            }
+            for marker in output.delete_markers.unwrap_or_default() {
+                if let (Some(key), Some(version_id)) = (marker.key, marker.version_id) {
+                    page.push(ObjectVersion {
+                        key,
+                        version_id,
+                        is_latest: marker.is_latest.unwrap_or_default(),
+                        is_delete_marker: true,
+                        last_modified: marker.last_modified,
+                        size: None,
+                        e_tag: None,
+                    });
+                }
+            }
+            // S3 returns versions and delete markers in separate lists.
+            page.sort_by(|a, b| {
+                a.key
+                    .cmp(&b.key)
+                    .then_with(|| b.last_modified.cmp(&a.last_modified))
+            });
+            versions.extend(page);
+
+            match output.is_truncated {
+                Some(true) => {
+                    key_marker = output.next_key_marker;
+                    version_id_marker = output.next_version_id_marker;
+                }
+                _ => return Ok(versions),
+            }
+        }
+    }
+
+    /// Deletes the object at the key with a single DeleteObject. Deleting a missing key succeeds.
+    pub fn delete_object(&self) -> Result<(), S3PathError> {
+        self.delete_version(None)
+    }
+
+    /// Deletes the object at the key, or with a `version_id`, permanently deletes that version
+    /// of it instead of adding a delete marker.
+    #[tokio::main]
+    pub async fn delete_version(&self, version_id: Option<&str>) -> Result<(), S3PathError> {
+        let delete_object_request = DeleteObjectRequest {
+            bucket: self.bucket.name.to_string(),
+            expected_bucket_owner: self.expected_owner.clone(),
+            key: self.bucket.key.to_string(),
+            request_payer: self.request_payer(),
+            version_id: version_id.map(str::to_string),
+            ..Default::default()
+        };
+
+        match self
+            .timed(
+                "DeleteObject",
+                self.client.delete_object(delete_object_request),
+            )
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(process_error(Some(e), None, S3PathOp::DeleteObject)),
+        }
+    }
+
+    /// Deletes the marker object of the directory at the key, without looking for objects
+    /// under it. Nothing is deleted with [DirectoryMarker::None].
+    pub fn remove_marker(&self) -> Result<(), S3PathError> {
+        match self.directory_marker.marker_key(&self.bucket.key) {
+            Some(marker) => self.with_key(marker).delete_object(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn delete_objects(&self, keys: &[String]) -> Result<(), S3PathError> {
+        self.delete_keys(keys).into_result().map(|_| ())
+    }
+
+    /// Deletes `keys` in batches of 1000, carrying on past the keys or batches that fail, and
+    /// returns which keys were deleted and which were not.
+    pub fn delete_keys(&self, keys: &[String]) -> BatchResult {
+        let mut result = BatchResult::default();
+
+        for chunk in keys.chunks(MAX_KEYS_PER_DELETE) {
+            let delete_objects_request = DeleteObjectsRequest {
+                bucket: self.bucket.name.to_string(),
+                bypass_governance_retention: None,
+                delete: Delete {
+                    objects: chunk
+                        .iter()
+                        .map(|key| ObjectIdentifier {
+                            key: key.to_string(),
+                            version_id: None,
+                        })
+                        .collect(),
+                    quiet: Some(true),
+                },
+                expected_bucket_owner: self.expected_owner.clone(),
+                mfa: None,
+                request_payer: self.request_payer(),
+            };
+
+            match self.delete_objects_request(delete_objects_request) {
+                // In quiet mode, S3 only lists the keys it could not delete.
+                Ok(output) => {
+                    let failed = output
+                        .errors
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|error| {
+                            Some((error.key.clone()?, process_delete_error(&error)))
+                        })
+                        .collect::<HashMap<String, S3PathError>>();
+                    for key in chunk {
+                        match failed.get(key) {
+                            Some(error) => result.failed.push((key.to_string(), error.clone())),
+                            None => result.succeeded.push(key.to_string()),
+                        }
+                    }
+                }
+                Err(e) => {
+                    let e = process_error(Some(e), None, S3PathOp::DeleteObjects);
+                    result
+                        .failed
+                        .extend(chunk.iter().map(|key| (key.to_string(), e.clone())));
+                }
+            }
+        }
+
+        result
+    }
+
+    pub fn write_with_checksum(
+        &self,
+        contents: Vec<u8>,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<String, S3PathError> {
+        let checksum = algorithm.compute(&contents);
+
+        let mut request = self.raw_request("PUT")?;
+        request.add_header("x-amz-sdk-checksum-algorithm", algorithm.name());
+        request.add_header(algorithm.header(), &checksum);
+        request.set_payload(Some(contents));
+
+        let response = self.dispatch(request, S3PathOp::PutObject)?;
+        match response.headers.get(algorithm.header()) {
+            Some(stored) if *stored == checksum => Ok(checksum),
+            _ => Err(S3PathError::ChecksumMismatch),
+        }
+    }
+
+    pub fn write_if_match(&self, contents: Vec<u8>, e_tag: &ETag) -> Result<ETag, S3PathError> {
+        let mut request = self.raw_request("PUT")?;
+        request.add_header("If-Match", e_tag.as_str());
+        request.set_payload(Some(contents));
+
+        let response = self.dispatch(request, S3PathOp::PutObject)?;
+        response
+            .headers
+            .get("etag")
+            .map(|e_tag| ETag::from(e_tag.as_str()))
+            .ok_or(S3PathError::Unknown)
+    }
+
+    /// Writes `contents` with `If-None-Match: *`, so S3 itself refuses the write if an object
+    /// already exists at the key, even one created by a concurrent writer. S3 answers that case
+    /// with a 412, returned as [S3PathError::ObjectAlreadyExists].
+    pub fn write_if_none_match(
+        &self,
+        contents: Vec<u8>,
+        metadata: Option<&HashMap<String, String>>,
+        options: &UploadOptions,
+    ) -> Result<(), S3PathError> {
+        let mut request = self.put_if_none_match_request(metadata, options)?;
+        request.set_payload(Some(contents));
+
+        already_exists_on_conflict(self.dispatch(request, S3PathOp::PutObject))
+    }
+
+    /// Streams the object of `from` into this key like [S3Service::write_if_none_match], carrying
+    /// over the user metadata, content-type and web headers of `source`. Only the chunks in
+    /// flight are held in memory, so `source` can be up to the 5 GiB a single PUT accepts.
+    #[tokio::main]
+    pub async fn copy_if_none_match(
+        &self,
+        from: &S3Service,
+        source: &ObjectMetadata,
+    ) -> Result<(), S3PathError> {
+        let length = source.content_length.ok_or(S3PathError::Unknown)?;
+        let mut request = self.put_if_none_match_request(
+            source.metadata.as_ref(),
+            &UploadOptions::preserving(source),
+        )?;
+
+        let body = from.get_object_stream().await?;
+        request.set_payload_stream(StreamingBody::new_with_size(body, length as usize));
+
+        already_exists_on_conflict(self.send(request, S3PathOp::PutObject).await)
+    }
+
+    fn put_if_none_match_request(
+        &self,
+        metadata: Option<&HashMap<String, String>>,
+        options: &UploadOptions,
+    ) -> Result<SignedRequest, S3PathError> {
+        let mut request = self.raw_request("PUT")?;
+        request.add_header("If-None-Match", "*");
+        request.add_optional_header("content-type", options.content_type.as_ref());
+        request.add_optional_header("content-encoding", options.get_content_encoding());
+        request.add_optional_header("cache-control", options.get_cache_control());
+        request.add_optional_header("content-disposition", options.get_content_disposition());
+        request.add_optional_header("expires", options.get_expires());
+        for (key, value) in metadata.into_iter().flatten() {
+            request.add_header(format!("x-amz-meta-{}", key), value);
+        }
+
+        Ok(request)
+    }
+
+    /// Runs the SQL `expression` over the object with S3 Select, returning the matching
+    /// records serialized as `output`.
+    pub fn select(
+        &self,
+        expression: &str,
+        input: &SelectInput,
+        output: &SelectOutput,
+    ) -> Result<Vec<u8>, S3PathError> {
+        let mut request = self.raw_request("POST")?;
+        request.add_param("select", "");
+        request.add_param("select-type", "2");
+        request.set_payload(Some(select::request_body(expression, input, output)));
+
+        let response = self.dispatch(request, S3PathOp::SelectObjectContent)?;
+        select::records(&response.body)
+    }
+
+    pub fn get_checksum(
+        &self,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<Option<String>, S3PathError> {
+        let mut request = self.raw_request("HEAD")?;
+        request.add_header("x-amz-checksum-mode", "ENABLED");
+
+        let response = self.dispatch(request, S3PathOp::HeadObject)?;
+        Ok(response.headers.get(algorithm.header()).cloned())
+    }
+}
+
+/// Maps the 412 S3 answers a write with `If-None-Match: *` on an existing key to
+/// [S3PathError::ObjectAlreadyExists].
+fn already_exists_on_conflict(
+    response: Result<BufferedHttpResponse, S3PathError>,
+) -> Result<(), S3PathError> {
+    match response {
+        Ok(_) => Ok(()),
+        Err(S3PathError::PreconditionFailed) => Err(S3PathError::ObjectAlreadyExists),
+        Err(e) => Err(e),
+    }
+}
+
+/// Checks the ETag S3 returned for an upload against the `expected` one computed locally.
+fn verify_upload(result: PutObjectOutput, expected: &ETag) -> Result<PutObjectOutput, S3PathError> {
+    match result.e_tag.as_deref().map(ETag::from) {
+        Some(e_tag) if e_tag == *expected => Ok(result),
+        _ => Err(S3PathError::ChecksumMismatch),
+    }
+}
+
+/// Whether `request` places an Object Lock retention or legal hold, for which S3 requires a
+/// Content-MD5 on every request that carries a body.
+fn locks_object(request: &PutObjectRequest) -> bool {
+    request.object_lock_mode.is_some() || request.object_lock_legal_hold_status.is_some()
+}
+
+/// The base64 encoded MD5 of `bytes`, as sent in a Content-MD5 header.
+fn content_md5(bytes: &[u8]) -> String {
+    base64::encode(Md5::digest(bytes))
+}
+
+/// Reads up to `size` bytes, which is only fewer at the end of the body.
+async fn read_part(
+    reader: &mut (impl AsyncRead + Unpin),
+    size: usize,
+) -> Result<Vec<u8>, S3PathError> {
+    let mut part = Vec::with_capacity(size);
+    reader
+        .take(size as u64)
+        .read_to_end(&mut part)
+        .await
+        .map_err(|_| S3PathError::Unknown)?;
+
+    Ok(part)
+}
+
+/// Adapts a blocking `Read` to `AsyncRead`. Each read blocks the thread polling it, which the
+/// runtime of a `#[tokio::main]` call, made for that one upload, can afford.
+struct BlockingReader<'a>(&'a mut dyn Read);
+
+impl AsyncRead for BlockingReader<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let read = self.0.read(buf.initialize_unfilled())?;
+        buf.advance(read);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Takes the next part of `part_size` bytes from `pending`, reading the rest from `reader`.
+/// The part is only shorter at the end of the body, and empty once it has all been read.
+async fn next_part(
+    pending: &mut Vec<u8>,
+    reader: &mut (impl AsyncRead + Unpin),
+    part_size: usize,
+) -> Result<Vec<u8>, S3PathError> {
+    if pending.len() >= part_size {
+        let rest = pending.split_off(part_size);
+        return Ok(std::mem::replace(pending, rest));
+    }
+
+    let mut part = std::mem::take(pending);
+    part.extend(read_part(reader, part_size - part.len()).await?);
+    Ok(part)
+}
+
+/// Whether `error` is worth retrying: S3 was throttling or briefly unavailable, or the request
+/// never got a response.
+fn is_transient<E>(error: &RusotoError<E>) -> bool {
+    match error {
+        RusotoError::HttpDispatch(_) => true,
+        RusotoError::Unknown(response) => response.status.is_server_error(),
+        _ => false,
+    }
+}
+
+/// Decodes the keys of a listing requested with the `url` encoding type, in which S3 encodes
+/// spaces as `+` and a literal `+` as `%2B`.
+fn decode_listing(mut output: ListObjectsV2Output) -> ListObjectsV2Output {
+    let decode = |key: String| {
+        percent_decode_str(&key.replace('+', " "))
+            .decode_utf8_lossy()
+            .into_owned()
+    };
+
+    for object in output.contents.iter_mut().flatten() {
+        object.key = object.key.take().map(decode);
+    }
+    for common_prefix in output.common_prefixes.iter_mut().flatten() {
+        common_prefix.prefix = common_prefix.prefix.take().map(decode);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusoto_s3::{CommonPrefix, Object};
+
+    #[test]
+    fn decode_listing_decodes_spaces_plus_and_newlines() {
+        let output = ListObjectsV2Output {
+            contents: Some(vec![Object {
+                key: Some("dir/a+b%2Bc%0Ad.txt".to_string()),
+                ..Default::default()
+            }]),
+            common_prefixes: Some(vec![CommonPrefix {
+                prefix: Some("my+dir/".to_string()),
+            }]),
+            ..Default::default()
+        };
+
+        let output = decode_listing(output);
+
+        assert_eq!(
+            output.contents.unwrap()[0].key.as_deref(),
+            Some("dir/a b+c\nd.txt")
+        );
+        assert_eq!(
+            output.common_prefixes.unwrap()[0].prefix.as_deref(),
+            Some("my dir/")
+        );
+    }
 }
@@ -0,0 +1,257 @@
+use crate::errors::S3PathError;
+use crate::etag::ETag;
+use crate::options::MetadataDirective;
+use crate::services::{S3Service, MAX_CONCURRENT_REQUESTS};
+use chrono::DateTime;
+use rusoto_s3::{CopyObjectOutput, Object};
+use std::collections::HashMap;
+
+/// Options controlling [crate::fs::sync].
+///
+/// ```
+///   use s3_fs::sync::SyncOptions;
+///   let options = SyncOptions::new().delete(true);
+///```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncOptions {
+    delete: bool,
+}
+
+impl SyncOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delete objects under the destination prefix that no longer exist under the source prefix.
+    pub fn delete(mut self, delete: bool) -> Self {
+        self.delete = delete;
+        self
+    }
+}
+
+/// The keys, relative to the synced prefixes, that a sync copied or deleted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncReport {
+    pub copied: Vec<String>,
+    pub deleted: Vec<String>,
+    /// The keys that could not be copied or deleted, with the error of each.
+    pub failed: Vec<(String, S3PathError)>,
+}
+
+/// The outcome of a batch operation for each of its keys, so that the keys which failed can be
+/// reported without losing track of those that went through.
+///
+/// ```
+///   use s3_fs::errors::S3PathError;
+///   use s3_fs::sync::BatchResult;
+///   let result = BatchResult {
+///       succeeded: vec!["a.tmp".to_string()],
+///       failed: vec![("b.tmp".to_string(), S3PathError::Timeout)],
+///   };
+///   assert_eq!(result.into_result(), Err(S3PathError::Timeout));
+///```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchResult {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<(String, S3PathError)>,
+}
+
+impl BatchResult {
+    /// The keys that succeeded, or the error of the first key that failed, if any did.
+    pub fn into_result(self) -> Result<Vec<String>, S3PathError> {
+        match self.failed.into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(self.succeeded),
+        }
+    }
+}
+
+/// The keys, relative to the moved prefixes, that a move relocated or left in place.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MoveReport {
+    pub moved: Vec<String>,
+    /// The keys that are still under the source prefix, with the error that kept them there.
+    pub failed: Vec<(String, S3PathError)>,
+}
+
+pub(crate) fn sync(
+    src: &S3Service,
+    dst: &S3Service,
+    options: &SyncOptions,
+) -> Result<SyncReport, S3PathError> {
+    let src_prefix = dir_prefix(&src.bucket.key);
+    let dst_prefix = dir_prefix(&dst.bucket.key);
+
+    let src_objects = relative_objects(&src.with_key(&src_prefix), &src_prefix)?;
+    let mut dst_objects = relative_objects(&dst.with_key(&dst_prefix), &dst_prefix)?;
+
+    let mut report = SyncReport::default();
+
+    let mut relative_keys = src_objects.keys().collect::<Vec<&String>>();
+    relative_keys.sort();
+
+    for relative_key in relative_keys {
+        let src_object = &src_objects[relative_key];
+        let changed = match dst_objects.remove(relative_key) {
+            Some(dst_object) => is_changed(src_object, &dst_object),
+            None => true,
+        };
+
+        if changed {
+            let copied = dst
+                .with_key(format!("{}{}", dst_prefix, relative_key))
+                .copy_from(
+                    &src.with_key(format!("{}{}", src_prefix, relative_key)),
+                    &MetadataDirective::Copy,
+                );
+            match copied {
+                Ok(_) => report.copied.push(relative_key.to_string()),
+                Err(e) => report.failed.push((relative_key.to_string(), e)),
+            }
+        }
+    }
+
+    if options.delete && !dst_objects.is_empty() {
+        let mut extraneous = dst_objects.into_keys().collect::<Vec<String>>();
+        extraneous.sort();
+
+        let keys = extraneous
+            .iter()
+            .map(|relative_key| format!("{}{}", dst_prefix, relative_key))
+            .collect::<Vec<String>>();
+        let deleted = dst.delete_keys(&keys);
+        report.deleted = relative_to(&dst_prefix, deleted.succeeded);
+        report.failed.extend(
+            deleted
+                .failed
+                .into_iter()
+                .map(|(key, e)| (key[dst_prefix.len()..].to_string(), e)),
+        );
+    }
+
+    Ok(report)
+}
+
+pub(crate) fn move_dir(src: &S3Service, dst: &S3Service) -> Result<MoveReport, S3PathError> {
+    let src_prefix = dir_prefix(&src.bucket.key);
+    let dst_prefix = dir_prefix(&dst.bucket.key);
+
+    let mut relative_keys = relative_objects(&src.with_key(&src_prefix), &src_prefix)?
+        .into_keys()
+        .collect::<Vec<String>>();
+    relative_keys.sort();
+
+    let mut report = MoveReport::default();
+    for (relative_key, result) in copy_all(src, dst, &src_prefix, &dst_prefix, relative_keys) {
+        match result {
+            Ok(_) => report.moved.push(relative_key),
+            Err(e) => report.failed.push((relative_key, e)),
+        }
+    }
+
+    // Sources are only deleted once copied, so a failure never loses an object.
+    let keys = report
+        .moved
+        .iter()
+        .map(|relative_key| format!("{}{}", src_prefix, relative_key))
+        .collect::<Vec<String>>();
+    let deleted = src.delete_keys(&keys);
+    report.moved = relative_to(&src_prefix, deleted.succeeded);
+    report.failed.extend(
+        deleted
+            .failed
+            .into_iter()
+            .map(|(key, e)| (key[src_prefix.len()..].to_string(), e)),
+    );
+
+    Ok(report)
+}
+
+/// Strips `prefix` from each of `keys`, which all start with it.
+fn relative_to(prefix: &str, keys: Vec<String>) -> Vec<String> {
+    keys.into_iter()
+        .map(|key| key[prefix.len()..].to_string())
+        .collect()
+}
+
+/// Copies every relative key from `src_prefix` to `dst_prefix` server-side, concurrently,
+/// returning the result of each copy in the order of `relative_keys`.
+#[tokio::main]
+async fn copy_all(
+    src: &S3Service,
+    dst: &S3Service,
+    src_prefix: &str,
+    dst_prefix: &str,
+    relative_keys: Vec<String>,
+) -> Vec<(String, Result<CopyObjectOutput, S3PathError>)> {
+    let keys = relative_keys
+        .iter()
+        .map(|relative_key| format!("{}{}", dst_prefix, relative_key))
+        .collect();
+    let results = dst
+        .batch(keys, MAX_CONCURRENT_REQUESTS, |service| {
+            let relative_key = &service.bucket.key[dst_prefix.len()..];
+            let from = src.with_key(format!("{}{}", src_prefix, relative_key));
+            async move {
+                service
+                    .copy_object_from(&from, &MetadataDirective::Copy)
+                    .await
+            }
+        })
+        .await;
+
+    relative_keys
+        .into_iter()
+        .zip(results)
+        .map(|(relative_key, (_, result))| (relative_key, result))
+        .collect()
+}
+
+pub(crate) fn dir_prefix(key: &str) -> String {
+    match key.is_empty() || key.ends_with('/') {
+        true => key.to_string(),
+        false => format!("{}/", key),
+    }
+}
+
+fn relative_objects(
+    service: &S3Service,
+    prefix: &str,
+) -> Result<HashMap<String, Object>, S3PathError> {
+    Ok(service
+        .list_all_objects()?
+        .into_iter()
+        .filter_map(|object| {
+            let relative_key = object.key.as_ref()?.strip_prefix(prefix)?.to_string();
+            match relative_key.is_empty() {
+                true => None,
+                false => Some((relative_key, object)),
+            }
+        })
+        .collect())
+}
+
+/// An object is copied again when its size differs, or when its ETag differs and the source was
+/// modified after the destination. Differing ETags alone prove nothing: multipart ETags depend on
+/// the part size, and those of SSE-KMS objects are not MD5s, which a listing does not tell apart.
+/// This keeps re-runs idempotent even when the copy ends up with a different ETag than its source.
+fn is_changed(src: &Object, dst: &Object) -> bool {
+    if src.size != dst.size {
+        return true;
+    }
+    let e_tag = |object: &Object| object.e_tag.as_deref().map(ETag::from);
+    if e_tag(src).is_some() && e_tag(src) == e_tag(dst) {
+        return false;
+    }
+
+    let parse = |object: &Object| {
+        object
+            .last_modified
+            .as_ref()
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+    };
+    match (parse(src), parse(dst)) {
+        (Some(src_modified), Some(dst_modified)) => src_modified > dst_modified,
+        _ => true,
+    }
+}
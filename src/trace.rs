@@ -0,0 +1,36 @@
+use crate::bucket::BucketConfig;
+use rusoto_core::RusotoError;
+use std::fmt::Debug;
+use std::future::Future;
+use std::time::Instant;
+use tracing::Instrument;
+
+/// Runs `request` inside a `s3_request` span naming the operation, bucket and key, and records
+/// an event with its duration in milliseconds once it completes or fails.
+pub(crate) async fn instrument<T, E: Debug>(
+    bucket: &BucketConfig,
+    operation: &str,
+    request: impl Future<Output = Result<T, RusotoError<E>>>,
+) -> Result<T, RusotoError<E>> {
+    let span = tracing::info_span!(
+        "s3_request",
+        operation,
+        bucket = %bucket.name,
+        key = %bucket.key,
+    );
+
+    async move {
+        let started = Instant::now();
+        let result = request.await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        match &result {
+            Ok(_) => tracing::debug!(duration_ms, "request succeeded"),
+            Err(error) => tracing::warn!(duration_ms, ?error, "request failed"),
+        }
+
+        result
+    }
+    .instrument(span)
+    .await
+}